@@ -1,11 +1,14 @@
 #[macro_use]
 extern crate nom;
+extern crate parse_error;
 
 use std::str;
 use std::collections::HashMap;
 
 use nom::digit;
 
+pub use parse_error::ParseError;
+
 named!(
     number<u32>,
     map_res!(map_res!(digit, str::from_utf8), str::parse)
@@ -21,11 +24,8 @@ named!(
     do_parse!(n: number >> tag!(" <-> ") >> c: children >> (n, c))
 );
 
-pub fn parse(input: &str) -> HashMap<u32, Vec<u32>> {
-    input
-        .lines()
-        .filter_map(|l| line(l.as_bytes()).to_result().ok())
-        .collect()
+pub fn parse(input: &str) -> Result<HashMap<u32, Vec<u32>>, ParseError> {
+    Ok(parse_error::parse_lines(input, line)?.into_iter().collect())
 }
 
 #[cfg(test)]
@@ -47,4 +47,12 @@ mod tests {
             Done(EMPTY, (2, vec![0, 3, 4]))
         )
     }
+
+    #[test]
+    fn trailing_separator() {
+        let err = parse("0 <-> 2\n2 <-> 0, 3,").unwrap_err();
+
+        assert_eq!((err.line, err.column), (2, 11));
+        assert_eq!(err.kind, parse_error::ErrorKind::TrailingInput);
+    }
 }
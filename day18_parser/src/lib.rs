@@ -1,87 +1,23 @@
 #[macro_use]
 extern crate nom;
+extern crate parse_error;
+extern crate vm;
 
-use nom::{anychar, digit, space};
-use std::str;
+use vm::grammar::*;
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
-pub enum Instruction {
-    Snd(Value),
-    Set(char, Value),
-    Add(char, Value),
-    Mul(char, Value),
-    Mod(char, Value),
-    Rcv(char),
-    Jgz(Value, Value),
-}
-
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
-pub enum Value {
-    Register(char),
-    Literal(i64),
-}
-
-named!(register<char>, verify!(anychar, |c| c >= 'a' && c <= 'z'));
-named!(
-    literal<i64>,
-    map_res!(
-        map_res!(recognize!(pair!(opt!(tag!("-")), digit)), str::from_utf8),
-        str::parse
-    )
-);
-
-named!(
-    value<Value>,
-    alt!(map!(register, Value::Register) | map!(literal, Value::Literal))
-);
-
-named!(
-    snd<Instruction>,
-    do_parse!(tag!("snd ") >> val: value >> (Instruction::Snd(val)))
-);
-
-named!(
-    rcv<Instruction>,
-    do_parse!(tag!("rcv ") >> reg: register >> (Instruction::Rcv(reg)))
-);
+pub use parse_error::ParseError;
+pub use vm::assembler::disassemble;
+pub use vm::{Instruction, Value};
 
-named!(
-    set<Instruction>,
-    do_parse!(tag!("set ") >> reg: register >> space >> val: value >> (Instruction::Set(reg, val)))
-);
+named!(line<Instruction>, alt!(snd | set | add | mul | modulo | rcv | jgz));
 
-named!(
-    add<Instruction>,
-    do_parse!(tag!("add ") >> reg: register >> space >> val: value >> (Instruction::Add(reg, val)))
-);
-
-named!(
-    mul<Instruction>,
-    do_parse!(tag!("mul ") >> reg: register >> space >> val: value >> (Instruction::Mul(reg, val)))
-);
-
-named!(
-    modulo<Instruction>,
-    do_parse!(tag!("mod ") >> reg: register >> space >> val: value >> (Instruction::Mod(reg, val)))
-);
-
-named!(
-    jgz<Instruction>,
-    do_parse!(
-        tag!("jgz ") >> val: value >> space >> offset: value >> (Instruction::Jgz(val, offset))
-    )
-);
-
-named!(
-    line<Instruction>,
-    alt!(snd | set | add | mul | modulo | rcv | jgz)
-);
+pub fn parse(input: &str) -> Result<Vec<Instruction>, ParseError> {
+    parse_error::parse_lines(input, line)
+}
 
-pub fn parse(input: &str) -> Vec<Instruction> {
-    input
-        .lines()
-        .filter_map(|l| line(l.as_bytes()).to_result().ok())
-        .collect()
+/// Parses a program written with labels and comments, see `vm::assembler`.
+pub fn assemble(source: &str) -> Result<Vec<Instruction>, ParseError> {
+    vm::assembler::assemble(source, line)
 }
 
 #[cfg(test)]
@@ -103,7 +39,7 @@ rcv a
 jgz a -1
 set a 1
 jgz a -2",
-        );
+        ).unwrap();
 
         assert_eq!(
             instructions,
@@ -112,7 +48,7 @@ jgz a -2",
                 Add('a', Literal(2)),
                 Mul('a', Register('a')),
                 Mod('a', Literal(5)),
-                Snd('a'),
+                Snd(Register('a')),
                 Set('a', Literal(0)),
                 Rcv('a'),
                 Jgz(Register('a'), Literal(-1)),
@@ -121,4 +57,40 @@ jgz a -2",
             ]
         )
     }
+
+    #[test]
+    fn truncated_line() {
+        let err = parse("set a 1\nsnd\nrcv a").unwrap_err();
+
+        assert_eq!(
+            err,
+            ParseError::new(2, 4, "snd", parse_error::ErrorKind::Incomplete)
+        );
+    }
+
+    #[test]
+    fn assembled_sample() {
+        let program = assemble(
+            "; the sample, with its jumps named
+    set a 1
+    add a 2
+    mul a a
+    mod a 5
+    snd a
+    set a 0
+recover:
+    rcv a
+check:
+    jgz a recover
+    set a 1
+    jgz a check",
+        ).unwrap();
+
+        assert_eq!(
+            program,
+            parse("set a 1\nadd a 2\nmul a a\nmod a 5\nsnd a\nset a 0\nrcv a\njgz a -1\nset a 1\njgz a -2")
+                .unwrap()
+        );
+        assert_eq!(assemble(&disassemble(&program)), Ok(program));
+    }
 }
@@ -1,13 +1,16 @@
 #[macro_use]
 extern crate nom;
+extern crate parse_error;
 
 use std::str;
 use std::cmp::Ordering;
 
-use std::ops::{Add, Mul};
+use std::ops::{Add, Mul, Sub};
 
 use nom::digit;
 
+pub use parse_error::ParseError;
+
 pub type Int = i64;
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
@@ -18,6 +21,15 @@ pub struct Vec3 {
 }
 
 impl Vec3 {
+    pub fn new(x: Int, y: Int, z: Int) -> Vec3 {
+        Vec3 { x, y, z }
+    }
+
+    /// `x`, `y` and `z`, in that order.
+    pub fn axes(&self) -> [Int; 3] {
+        [self.x, self.y, self.z]
+    }
+
     pub fn distance(&self) -> Int {
         self.x.abs() + self.y.abs() + self.z.abs()
     }
@@ -47,6 +59,18 @@ impl Add<Vec3> for Vec3 {
     }
 }
 
+impl Sub<Vec3> for Vec3 {
+    type Output = Vec3;
+
+    fn sub(self, rhs: Vec3) -> Self::Output {
+        Vec3 {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+
 impl Mul<Int> for Vec3 {
     type Output = Vec3;
 
@@ -83,12 +107,11 @@ named!(
     )
 );
 
-pub fn parse<T>(input: &str, mapper: fn((Vec3, Vec3, Vec3)) -> T) -> Vec<T> {
-    input
-        .lines()
-        .map(|l| line(l.as_bytes()).to_result().unwrap())
+pub fn parse<T>(input: &str, mapper: fn((Vec3, Vec3, Vec3)) -> T) -> Result<Vec<T>, ParseError> {
+    Ok(parse_error::parse_lines(input, line)?
+        .into_iter()
         .map(mapper)
-        .collect()
+        .collect())
 }
 
 #[cfg(test)]
@@ -1,5 +1,10 @@
 #[macro_use]
 extern crate nom;
+extern crate parse_error;
+
+pub use parse_error::ParseError;
+
+pub type Pattern = Vec<Vec<bool>>;
 
 named!(
     pixel<bool>,
@@ -9,21 +14,20 @@ named!(
 named!(pixels<Vec<bool>>, many1!(pixel));
 
 named!(
-    grid<Vec<Vec<bool>>>,
+    grid<Pattern>,
     separated_list_complete!(tag!("/"), pixels)
 );
 
 named!(
-    input_row<(Vec<Vec<bool>>, Vec<Vec<bool>>)>,
+    input_row<(Pattern, Pattern)>,
     do_parse!(pattern: grid >> tag!(" => ") >> replace: grid >> (pattern, replace))
 );
 
-pub fn parse<T>(input: &str, mapper: fn((Vec<Vec<bool>>, Vec<Vec<bool>>)) -> T) -> Vec<T> {
-    input
-        .lines()
-        .map(|line| input_row(line.as_bytes()).to_result().unwrap())
+pub fn parse<T>(input: &str, mapper: fn((Pattern, Pattern)) -> T) -> Result<Vec<T>, ParseError> {
+    Ok(parse_error::parse_lines(input, input_row)?
+        .into_iter()
         .map(mapper)
-        .collect()
+        .collect())
 }
 
 #[cfg(test)]
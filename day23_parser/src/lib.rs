@@ -1,70 +1,23 @@
 #[macro_use]
 extern crate nom;
+extern crate parse_error;
+extern crate vm;
 
-use nom::{anychar, digit, space};
-use std::str;
+use vm::grammar::*;
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
-pub enum Instruction {
-    Set(char, Value),
-    Sub(char, Value),
-    Mul(char, Value),
-    Jnz(Value, Value),
-}
-
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
-pub enum Value {
-    Register(char),
-    Literal(i64),
-}
-
-named!(register<char>, verify!(anychar, |c| c >= 'a' && c <= 'z'));
-named!(
-    literal<i64>,
-    map_res!(
-        map_res!(recognize!(pair!(opt!(tag!("-")), digit)), str::from_utf8),
-        str::parse
-    )
-);
-
-named!(
-    value<Value>,
-    alt!(map!(register, Value::Register) | map!(literal, Value::Literal))
-);
-
-named!(
-    set<Instruction>,
-    do_parse!(tag!("set ") >> reg: register >> space >> val: value >> (Instruction::Set(reg, val)))
-);
-
-named!(
-    sub<Instruction>,
-    do_parse!(tag!("sub ") >> reg: register >> space >> val: value >> (Instruction::Sub(reg, val)))
-);
-
-named!(
-    mul<Instruction>,
-    do_parse!(tag!("mul ") >> reg: register >> space >> val: value >> (Instruction::Mul(reg, val)))
-);
-
-named!(
-    jnz<Instruction>,
-    do_parse!(
-        tag!("jnz ") >> val: value >> space >> offset: value >> (Instruction::Jnz(val, offset))
-    )
-);
+pub use parse_error::ParseError;
+pub use vm::assembler::disassemble;
+pub use vm::{Instruction, Value};
 
 named!(line<Instruction>, alt!(set | sub | mul | jnz));
 
-pub fn parse(input: &str) -> Vec<Instruction> {
-    input
-        .lines()
-        .map(|l| {
-            line(l.as_bytes())
-                .to_result()
-                .expect("Failed to parse input")
-        })
-        .collect()
+pub fn parse(input: &str) -> Result<Vec<Instruction>, ParseError> {
+    parse_error::parse_lines(input, line)
+}
+
+/// Parses a program written with labels and comments, see `vm::assembler`.
+pub fn assemble(source: &str) -> Result<Vec<Instruction>, ParseError> {
+    vm::assembler::assemble(source, line)
 }
 
 #[cfg(test)]
@@ -162,4 +115,24 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn unknown_instruction() {
+        let err = parse("set a 1\njmp a 2").unwrap_err();
+
+        assert_eq!(
+            err,
+            ParseError::new(2, 1, "jmp a 2", parse_error::ErrorKind::Syntax)
+        );
+    }
+
+    #[test]
+    fn assemble_keeps_the_dialect() {
+        let err = assemble("top:\n    snd a\n    jnz 1 top").unwrap_err();
+
+        assert_eq!(
+            err,
+            ParseError::new(2, 5, "    snd a", parse_error::ErrorKind::Syntax)
+        );
+    }
 }
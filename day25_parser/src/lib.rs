@@ -1,15 +1,25 @@
 #[macro_use]
 extern crate nom;
+extern crate parse_error;
 
-use nom::{anychar, digit, line_ending};
+use nom::{anychar, digit, line_ending, rest, IResult};
 use std::str;
 
+pub use parse_error::{ErrorKind, ParseError};
+
 type Header = (char, u64);
 type InstructionList = (bool, i32, char);
 type Instruction = (char, (bool, InstructionList), (bool, InstructionList));
+/// An instruction, and the input left from its second condition.
+type Located<'a> = (Instruction, &'a [u8]);
 
 named!(state<char>, call!(anychar));
 
+/// The input left at this point, to locate what follows.
+fn position(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    peek!(input, call!(rest))
+}
+
 named!(
     number<u64>,
     map_res!(map_res!(digit, str::from_utf8), str::parse)
@@ -74,30 +84,52 @@ named!(
     ))
 );
 
-named!(
-    instruction<Instruction>,
-    ws!(do_parse!(
-        s: in_state >> cond_a: if_value >> inst_a: instruction_list >> cond_b: if_value
-            >> inst_b: instruction_list >> (s, (cond_a, inst_a), (cond_b, inst_b))
-    ))
-);
+fn instruction(input: &[u8]) -> IResult<&[u8], Located<'_>> {
+    ws!(
+        input,
+        do_parse!(
+            s: in_state >> cond_a: if_value >> inst_a: instruction_list >> at_b: position
+                >> cond_b: if_value >> inst_b: instruction_list
+                >> ((s, (cond_a, inst_a), (cond_b, inst_b)), at_b)
+        )
+    )
+}
 
-named!(
-    blueprint<(Header, Vec<Instruction>)>,
-    ws!(do_parse!(
-        h: header >> instructions: many1!(instruction) >> (h, instructions)
-    ))
-);
+fn blueprint(input: &[u8]) -> IResult<&[u8], (Header, Vec<Located<'_>>)> {
+    ws!(
+        input,
+        do_parse!(h: header >> instructions: many1!(instruction) >> (h, instructions))
+    )
+}
 
 pub fn parse<I, T>(
     input: &str,
     inst_mapper: fn(Instruction) -> I,
     bp_mapper: fn(Header, Vec<I>) -> T,
-) -> T {
-    blueprint(input.as_bytes())
-        .to_result()
-        .map(|(h, i)| bp_mapper(h, i.into_iter().map(inst_mapper).collect()))
-        .unwrap()
+) -> Result<T, ParseError> {
+    let (header, instructions) = parse_error::parse_complete(input, blueprint)?;
+
+    let instructions = instructions
+        .into_iter()
+        .map(|(instruction, at_b)| {
+            let (state, (cond_a, _), (cond_b, _)) = instruction;
+
+            if cond_a == cond_b {
+                return Err(ParseError::at_offset(
+                    input,
+                    input.len() - at_b.len(),
+                    ErrorKind::Invalid(format!(
+                        "state {} already has instructions for the value {}",
+                        state, cond_b as u8
+                    )),
+                ));
+            }
+
+            Ok(inst_mapper(instruction))
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(bp_mapper(header, instructions))
 }
 
 #[cfg(test)]
@@ -200,15 +232,39 @@ mod tests {
             instruction(inst),
             Done(
                 EMPTY,
-                ('A', (false, (true, 1, 'B')), (true, (false, -1, 'B')))
+                (
+                    ('A', (false, (true, 1, 'B')), (true, (false, -1, 'B'))),
+                    &inst[131..]
+                )
             )
         );
     }
 
+    #[test]
+    fn duplicate_condition() {
+        let input = String::from_utf8(include_bytes!("../resources/sample.txt").to_vec())
+            .unwrap()
+            .replacen("value is 1", "value is 0", 1);
+
+        assert_eq!(
+            parse(&input, |i| i, |_, i| i),
+            Err(ParseError::new(
+                9,
+                3,
+                "  If the current value is 0:",
+                ErrorKind::Invalid("state A already has instructions for the value 0".to_owned())
+            ))
+        );
+    }
+
     #[test]
     fn sample_input() {
+        let instructions = |(header, instructions): (Header, Vec<Located<'_>>)| {
+            (header, instructions.into_iter().map(|(i, _)| i).collect())
+        };
+
         assert_eq!(
-            blueprint(include_bytes!("../resources/sample.txt")),
+            blueprint(include_bytes!("../resources/sample.txt")).map(instructions),
             Done(
                 EMPTY,
                 (
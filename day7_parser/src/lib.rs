@@ -1,12 +1,15 @@
 #[macro_use]
 extern crate nom;
+extern crate parse_error;
 
 use std::str;
 use std::collections::HashMap;
 
 use nom::{alphanumeric, space};
 
-named!(name<&str>, map_res!(alphanumeric, str::from_utf8));
+pub use parse_error::ParseError;
+
+named!(name<&[u8], &str>, map_res!(alphanumeric, str::from_utf8));
 
 named!(
     weight<u32>,
@@ -22,24 +25,23 @@ named!(
 named!(child_sep, complete!(tag!(" -> ")));
 
 named!(
-    children<Vec<&str>>,
+    children<&[u8], Vec<&str>>,
     separated_list_complete!(tag!(", "), name)
 );
 
 named!(
-    line<(&str, u32, Vec<&str>)>,
+    line<&[u8], (&str, u32, Vec<&str>)>,
     do_parse!(n: name >> opt!(space) >> w: weight >> opt!(child_sep) >> c: children >> ((n, w, c)))
 );
 
 pub fn parse<'a, T>(
     input: &'a str,
     mapper: fn(&'a str, u32, Vec<&'a str>) -> T,
-) -> HashMap<&'a str, T> {
-    input
-        .lines()
-        .filter_map(|l| line(l.as_bytes()).to_result().ok())
-        .map(|(n, w, c)| (n.clone(), mapper(n, w, c)))
-        .collect()
+) -> Result<HashMap<&'a str, T>, ParseError> {
+    Ok(parse_error::parse_lines(input, line)?
+        .into_iter()
+        .map(|(n, w, c)| (n, mapper(n, w, c)))
+        .collect())
 }
 
 #[cfg(test)]
@@ -86,4 +88,11 @@ mod tests {
             Done(&b""[..], ("pbga", 66, vec![]))
         );
     }
+
+    #[test]
+    fn missing_weight() {
+        let err = parse("pbga (66)\nxhth 57", |n, _, _| n).unwrap_err();
+
+        assert_eq!((err.line, err.column), (2, 6));
+    }
 }
@@ -1,11 +1,14 @@
 #[macro_use]
 extern crate nom;
+extern crate parse_error;
 
 use std::str;
 
 use nom::{alpha, digit, space};
 
-named!(register<&str>, map_res!(alpha, str::from_utf8));
+pub use parse_error::ParseError;
+
+named!(register<&[u8], &str>, map_res!(alpha, str::from_utf8));
 
 named!(
     action<Action>,
@@ -34,7 +37,7 @@ named!(
 );
 
 named!(
-    line<(&str, Action, i32, &str, Operator, i32)>,
+    line<&[u8], (&str, Action, i32, &str, Operator, i32)>,
     do_parse!(
         reg: register >> space >> act: action >> space >> val: number >> tag!(" if ")
             >> oth_reg: register >> space >> cmp: operator >> space >> oth_val: number
@@ -85,10 +88,9 @@ impl Operator {
 pub fn parse<'a, T>(
     input: &'a str,
     mapper: fn(&'a str, Action, i32, &'a str, Operator, i32) -> T,
-) -> Vec<T> {
-    input
-        .lines()
-        .filter_map(|l| line(l.as_bytes()).to_result().ok())
+) -> Result<Vec<T>, ParseError> {
+    Ok(parse_error::parse_lines(input, line)?
+        .into_iter()
         .map(|(reg, act, val, oth_reg, cmp, oth_val)| mapper(reg, act, val, oth_reg, cmp, oth_val))
-        .collect()
+        .collect())
 }
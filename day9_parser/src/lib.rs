@@ -1,11 +1,13 @@
 #[macro_use]
 extern crate nom;
+extern crate parse_error;
 
 use std::str;
 
 use nom::anychar;
 
 pub use Content::{Garbage, Group};
+pub use parse_error::ParseError;
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum Content {
@@ -44,8 +46,8 @@ named!(
 
 named!(content<Content>, alt!(group | garbage));
 
-pub fn parse(input: &str) -> Result<Content, nom::ErrorKind> {
-    content(input.as_bytes()).to_result()
+pub fn parse(input: &str) -> Result<Content, ParseError> {
+    parse_error::parse_complete(input, content)
 }
 
 #[cfg(test)]
@@ -0,0 +1,198 @@
+#[cfg_attr(test, macro_use)]
+extern crate nom;
+
+use std::error::Error;
+use std::fmt;
+
+use nom::IResult;
+
+/// What went wrong while parsing a puzzle input.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ErrorKind {
+    /// The text doesn't match the grammar.
+    Syntax,
+    /// The text matches the grammar, but is followed by unexpected characters.
+    TrailingInput,
+    /// The text ends before the grammar is complete.
+    Incomplete,
+    /// The text is well formed, but its value is rejected.
+    Invalid(String),
+}
+
+/// A parse failure, located in the puzzle input.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseError {
+    /// Line of the failure, starting at 1.
+    pub line: usize,
+    /// Column of the failure in `text`, starting at 1.
+    pub column: usize,
+    /// The whole line that failed to parse.
+    pub text: String,
+    pub kind: ErrorKind,
+}
+
+impl ParseError {
+    pub fn new(line: usize, column: usize, text: &str, kind: ErrorKind) -> ParseError {
+        ParseError {
+            line,
+            column,
+            text: text.to_owned(),
+            kind,
+        }
+    }
+
+    /// Locates an error raised at `offset` bytes of a multi-line `input`.
+    pub fn at_offset(input: &str, offset: usize, kind: ErrorKind) -> ParseError {
+        let offset = offset.min(input.len());
+
+        let line_start = input[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line_end = input[offset..]
+            .find('\n')
+            .map(|i| offset + i)
+            .unwrap_or_else(|| input.len());
+
+        ParseError::new(
+            input[..line_start].matches('\n').count() + 1,
+            offset - line_start + 1,
+            input[line_start..line_end].trim_end_matches('\r'),
+            kind,
+        )
+    }
+
+    /// Moves an error found in a fragment of the input to its actual location.
+    pub fn shifted(mut self, line: usize, column_offset: usize, text: &str) -> ParseError {
+        self.line = line;
+        self.column += column_offset;
+        self.text = text.to_owned();
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let what = match self.kind {
+            ErrorKind::Syntax => "syntax error",
+            ErrorKind::TrailingInput => "unexpected trailing input",
+            ErrorKind::Incomplete => "unexpected end of line",
+            ErrorKind::Invalid(ref reason) => reason,
+        };
+
+        writeln!(f, "line {}, column {}: {}", self.line, self.column, what)?;
+        writeln!(f, "    {}", self.text)?;
+        write!(f, "    {:>1$}", "^", self.column)
+    }
+}
+
+impl Error for ParseError {}
+
+/// Runs a nom parser over the whole `input`, which must be entirely consumed.
+pub fn parse_complete<'a, O, F>(input: &'a str, parser: F) -> Result<O, ParseError>
+where
+    F: Fn(&'a [u8]) -> IResult<&'a [u8], O>,
+{
+    let offset = |rest: &[u8]| input.len() - rest.len();
+
+    match parser(input.as_bytes()) {
+        IResult::Done(rest, output) => {
+            if rest.is_empty() {
+                Ok(output)
+            } else {
+                Err(ParseError::at_offset(
+                    input,
+                    offset(rest),
+                    ErrorKind::TrailingInput,
+                ))
+            }
+        }
+        IResult::Error(err) => Err(ParseError::at_offset(
+            input,
+            error_position(&err).map(offset).unwrap_or(0),
+            ErrorKind::Syntax,
+        )),
+        IResult::Incomplete(_) => Err(ParseError::at_offset(
+            input,
+            input.len(),
+            ErrorKind::Incomplete,
+        )),
+    }
+}
+
+/// Runs a nom parser on every line of `input`, each line being entirely consumed.
+pub fn parse_lines<'a, O, F>(input: &'a str, parser: F) -> Result<Vec<O>, ParseError>
+where
+    F: Fn(&'a [u8]) -> IResult<&'a [u8], O>,
+{
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| parse_complete(line, &parser).map_err(|err| err.shifted(i + 1, 0, line)))
+        .collect()
+}
+
+/// The deepest position recorded by a verbose nom error.
+fn error_position<'a>(err: &nom::Err<&'a [u8]>) -> Option<&'a [u8]> {
+    match *err {
+        nom::Err::Code(_) => None,
+        nom::Err::Position(_, position) => Some(position),
+        nom::Err::Node(_, ref next) => next.iter().filter_map(error_position).next(),
+        nom::Err::NodePosition(_, position, ref next) => next
+            .iter()
+            .filter_map(error_position)
+            .next()
+            .or(Some(position)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    named!(digits<&[u8], &[u8]>, call!(nom::digit));
+
+    #[test]
+    fn complete() {
+        assert_eq!(parse_complete("42", digits), Ok(&b"42"[..]));
+    }
+
+    #[test]
+    fn trailing_input() {
+        assert_eq!(
+            parse_complete("42a", digits),
+            Err(ParseError::new(1, 3, "42a", ErrorKind::TrailingInput))
+        );
+    }
+
+    #[test]
+    fn syntax_error() {
+        assert_eq!(
+            parse_complete("a42", digits),
+            Err(ParseError::new(1, 1, "a42", ErrorKind::Syntax))
+        );
+    }
+
+    #[test]
+    fn error_on_line() {
+        assert_eq!(
+            parse_lines("1\n2\nx3\n4", digits),
+            Err(ParseError::new(3, 1, "x3", ErrorKind::Syntax))
+        );
+    }
+
+    #[test]
+    fn offset_in_multiline_input() {
+        assert_eq!(
+            ParseError::at_offset("abc\ndef\nghi", 5, ErrorKind::Syntax),
+            ParseError::new(2, 2, "def", ErrorKind::Syntax)
+        );
+    }
+
+    #[test]
+    fn display() {
+        let err = ParseError::new(2, 7, "set a !", ErrorKind::Syntax);
+
+        assert_eq!(
+            err.to_string(),
+            "line 2, column 7: syntax error\n    set a !\n          ^"
+        );
+    }
+}
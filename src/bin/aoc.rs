@@ -0,0 +1,156 @@
+extern crate aoc2017;
+extern crate time;
+
+use aoc2017::input::Source;
+use aoc2017::registry::{self, Day};
+
+use std::env;
+use std::error::Error;
+use std::process;
+
+const USAGE: &str = "Usage: aoc run <day> [--part 1|2] [INPUT]
+       aoc run --all [--part 1|2] [--inputs-dir <dir> | --embedded]
+
+INPUT:
+    --input <path>        read the puzzle input from a file, or stdin with -
+    --inputs-dir <dir>    read <dir>/dayN.txt, or the embedded input if missing
+    --embedded            use the inputs compiled in the binary
+
+Without INPUT, $AOC_INPUTS is used as --inputs-dir when set.";
+
+#[derive(Debug, Default)]
+struct Options {
+    day: Option<u32>,
+    all: bool,
+    part: Option<u32>,
+    input: Option<Source>,
+}
+
+impl Options {
+    fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        match args.next() {
+            Some(ref command) if command == "run" => (),
+            Some(command) => return Err(format!("Unknown command: {}", command)),
+            None => return Err("Missing command".to_owned()),
+        }
+
+        let mut options = Options::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--all" => options.all = true,
+                "--part" => {
+                    let part = args.next().ok_or("Missing value for --part")?;
+
+                    match part.as_str() {
+                        "1" => options.part = Some(1),
+                        "2" => options.part = Some(2),
+                        _ => return Err(format!("Invalid part: {}", part)),
+                    }
+                }
+                "--input" => {
+                    let path = args.next().ok_or("Missing value for --input")?;
+
+                    options.input = Some(if path == "-" {
+                        Source::Stdin
+                    } else {
+                        Source::File(path.into())
+                    });
+                }
+                "--inputs-dir" => {
+                    let dir = args.next().ok_or("Missing value for --inputs-dir")?;
+
+                    options.input = Some(Source::Directory(dir.into()));
+                }
+                "--embedded" => options.input = Some(Source::Embedded),
+                day => {
+                    let day = day.parse().map_err(|_| format!("Invalid day: {}", day))?;
+
+                    options.day = Some(day);
+                }
+            }
+        }
+
+        match (options.day, options.all, &options.input) {
+            (None, false, _) => Err("Missing day".to_owned()),
+            (Some(_), true, _) => Err("--all can't be used with a day".to_owned()),
+            (None, true, &Some(Source::File(_))) | (None, true, &Some(Source::Stdin)) => {
+                Err("--input can't be used with --all".to_owned())
+            }
+            _ => Ok(options),
+        }
+    }
+}
+
+fn run(day: &Day, source: &Source, part: Option<u32>) -> Result<(), Box<dyn Error>> {
+    let input = source.load(day.day)?;
+
+    let mut report = Vec::new();
+
+    if part != Some(2) {
+        let start = time::precise_time_s();
+
+        println!("Part 1 : {}", day.part1(&input)?);
+
+        report.push((1, time::precise_time_s() - start));
+    }
+
+    if part != Some(1) {
+        let start = time::precise_time_s();
+
+        if let Some(answer) = day.part2(&input) {
+            println!("Part 2 : {}", answer?);
+
+            report.push((2, time::precise_time_s() - start));
+        }
+    }
+
+    println!("\nREPORT:");
+    for (part, elapsed) in report {
+        println!("\t- Part {}: {:.9}s", part, elapsed);
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let options = Options::from_args(env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{}\n\n{}", err, USAGE);
+        process::exit(1);
+    });
+
+    let source = options.input.unwrap_or_else(Source::from_env);
+
+    if options.all {
+        let mut failed = false;
+
+        for day in registry::days() {
+            println!("=== Day {} ===", day.day);
+
+            if let Err(err) = run(&day, &source, options.part) {
+                eprintln!("Day {} failed: {}", day.day, err);
+                failed = true;
+            }
+
+            println!();
+        }
+
+        if failed {
+            process::exit(1);
+        }
+
+        return;
+    }
+
+    let number = options.day.expect("Missing day");
+
+    let day = registry::day(number).unwrap_or_else(|| {
+        eprintln!("Day {} is not registered", number);
+        process::exit(1);
+    });
+
+    if let Err(err) = run(&day, &source, options.part) {
+        eprintln!("Day {} failed: {}", number, err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,251 @@
+extern crate aoc2017;
+extern crate day18_parser;
+extern crate day23_parser;
+extern crate vm;
+
+use aoc2017::input::Source;
+use vm::debugger::{Debugger, Stop};
+use vm::{Instruction, Io, LetterRegisters, Machine, Mailbox, Registers, Sound};
+
+use std::env;
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+const USAGE: &str = "Usage: debugger <18|23> [--input <path>] [--io <mailbox|sound>]
+
+Runs the day's program step by step, the embedded input being used by default.
+With mailbox devices (the default), snd and rcv use message queues; with sound
+devices, snd plays a sound and rcv recovers it, as in day 18 part 1.";
+
+const HELP: &str = "Commands:
+    s, step [n]             execute n instructions (1 by default)
+    c, continue             run until a breakpoint or a halt
+    b, break <pc>           stop before executing the instruction at pc
+    b, break <r> <op> <v>   stop when register r satisfies op (== != < <= > >=) v
+    d, delete <n>           remove the nth breakpoint
+    breakpoints             list the breakpoints
+    r, registers            print the non-zero registers
+    set <r> <v>             set register r to v
+    queues                  print the received and sent messages (mailbox)
+    send <v>                add v to the received messages (mailbox)
+    sound                   print the sounds played and recovered (sound)
+    l, list                 print the instructions around the program counter
+    trace on|off            print every executed instruction
+    h, help                 print this help
+    quit                    exit the debugger";
+
+type VmDebugger<O> = Debugger<Instruction, LetterRegisters, O>;
+
+/// What `snd` and `rcv` do in the debugged machine.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+enum Devices {
+    Mailbox,
+    Sound,
+}
+
+/// Commands inspecting or feeding the devices.
+trait Inspect: Io {
+    /// Runs `command`, or returns `None` if these devices don't know it.
+    fn execute(&mut self, command: &str, args: &[&str]) -> Option<Result<(), Box<dyn Error>>>;
+}
+
+impl Inspect for Mailbox {
+    fn execute(&mut self, command: &str, args: &[&str]) -> Option<Result<(), Box<dyn Error>>> {
+        match (command, args) {
+            ("queues", []) => {
+                println!("received: {:?}", self.inbox);
+                println!("sent:     {:?}", self.outbox);
+                Some(Ok(()))
+            }
+            ("send", [value]) => Some(
+                value
+                    .parse()
+                    .map(|value| self.inbox.push_back(value))
+                    .map_err(Box::from),
+            ),
+            _ => None,
+        }
+    }
+}
+
+impl Inspect for Sound {
+    fn execute(&mut self, command: &str, args: &[&str]) -> Option<Result<(), Box<dyn Error>>> {
+        match (command, args) {
+            ("sound", []) => {
+                println!("played:    {:?}", self.played);
+                println!("recovered: {:?}", self.recovered);
+                Some(Ok(()))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn load(args: &[String]) -> Result<(Vec<Instruction>, Devices), Box<dyn Error>> {
+    let day = match args.first().map(String::as_str) {
+        Some("18") => 18,
+        Some("23") => 23,
+        Some(day) => return Err(format!("Day {} doesn't run on the vm", day).into()),
+        None => return Err("Missing day".into()),
+    };
+
+    let mut source = Source::Embedded;
+    let mut devices = Devices::Mailbox;
+
+    for option in args[1..].chunks(2) {
+        match (option[0].as_str(), option.get(1).map(String::as_str)) {
+            // The commands are read from stdin.
+            ("--input", Some("-")) => return Err("The program can't be read from stdin".into()),
+            ("--input", Some(path)) => source = Source::File(path.into()),
+            ("--io", Some("mailbox")) => devices = Devices::Mailbox,
+            ("--io", Some("sound")) => devices = Devices::Sound,
+            _ => return Err("Invalid arguments".into()),
+        }
+    }
+
+    let input = source.load(day)?;
+
+    let program = if day == 18 {
+        day18_parser::parse(input.trim())?
+    } else {
+        day23_parser::parse(input.trim())?
+    };
+
+    Ok((program, devices))
+}
+
+fn list<O: Io>(debugger: &VmDebugger<O>) {
+    let pc = debugger.machine().program_counter();
+
+    for (i, instruction) in debugger.machine().program().iter().enumerate() {
+        let i = i as i64;
+
+        if (i - pc).abs() <= 3 {
+            let marker = if i == pc { "=>" } else { "  " };
+            println!("{} {:>4}: {}", marker, i, instruction);
+        }
+    }
+}
+
+fn print_stop<O: Io>(debugger: &VmDebugger<O>, stop: Stop) {
+    match stop {
+        Stop::Breakpoint(index) => println!(
+            "Breakpoint {} ({}) hit after {} steps",
+            index,
+            debugger.breakpoints()[index],
+            debugger.steps()
+        ),
+        Stop::Halt(halt) => println!("Halted: {} after {} steps", halt, debugger.steps()),
+    }
+}
+
+fn execute<O: Inspect>(debugger: &mut VmDebugger<O>, line: &str) -> Result<bool, Box<dyn Error>> {
+    let mut words = line.split_whitespace();
+
+    let command = match words.next() {
+        Some(command) => command,
+        None => return Ok(true),
+    };
+
+    let rest = words.collect::<Vec<_>>();
+
+    match (command, &rest[..]) {
+        ("s", args) | ("step", args) => {
+            let n = args.first().map_or(Ok(1), |n| n.parse::<u64>())?;
+
+            for _ in 0..n {
+                if let Err(halt) = debugger.step() {
+                    print_stop(debugger, Stop::Halt(halt));
+                    break;
+                }
+            }
+
+            list(debugger);
+        }
+        ("c", []) | ("continue", []) => {
+            let stop = debugger.run();
+            print_stop(debugger, stop);
+            list(debugger);
+        }
+        ("b", args) | ("break", args) => {
+            let index = debugger.add_breakpoint(args.join(" ").parse()?);
+            println!("Breakpoint {} set", index);
+        }
+        ("d", [index]) | ("delete", [index]) => {
+            if debugger.remove_breakpoint(index.parse()?).is_none() {
+                return Err(format!("No breakpoint {}", index).into());
+            }
+        }
+        ("breakpoints", []) => {
+            for (i, breakpoint) in debugger.breakpoints().iter().enumerate() {
+                println!("{}: {}", i, breakpoint);
+            }
+        }
+        ("r", []) | ("registers", []) => {
+            println!("pc = {}", debugger.machine().program_counter());
+
+            for (reg, value) in debugger.machine().registers().values() {
+                println!("{} = {}", reg, value);
+            }
+        }
+        ("set", [reg, value]) => {
+            let reg = match reg.chars().next() {
+                Some(c) if reg.len() == 1 && c.is_ascii_lowercase() => c,
+                _ => return Err(format!("Invalid register: {}", reg).into()),
+            };
+
+            debugger.machine_mut().set(reg, value.parse()?);
+        }
+        ("l", []) | ("list", []) => list(debugger),
+        ("trace", ["on"]) => debugger.trace_to(io::stdout()),
+        ("trace", ["off"]) => debugger.stop_trace(),
+        ("h", []) | ("help", []) => println!("{}", HELP),
+        ("quit", []) => return Ok(false),
+        (command, args) => match debugger.io_mut().execute(command, args) {
+            Some(result) => result?,
+            None => return Err(format!("Invalid command: {}, try help", line.trim()).into()),
+        },
+    }
+
+    Ok(true)
+}
+
+fn repl<O: Inspect>(program: Vec<Instruction>, io: O) {
+    let mut debugger = Debugger::new(Machine::new(program), io);
+
+    list(&debugger);
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    loop {
+        print!("(vm) ");
+        io::stdout().flush().expect("failed to flush stdout");
+
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+
+        match execute(&mut debugger, &line) {
+            Ok(true) => (),
+            Ok(false) => break,
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let (program, devices) = load(&args).unwrap_or_else(|err| {
+        eprintln!("{}\n\n{}", err, USAGE);
+        process::exit(1);
+    });
+
+    match devices {
+        Devices::Mailbox => repl(program, Mailbox::default()),
+        Devices::Sound => repl(program, Sound::default()),
+    }
+}
@@ -0,0 +1,43 @@
+extern crate aoc2017;
+
+use aoc2017::knot_hasher::{Digest, KnotHashBuilder, Throughput};
+
+use std::env;
+use std::process;
+
+const USAGE: &str = "Usage: knot_bench [keys]
+
+Hashes `keys` day 14 style keys (16384 by default) one at a time, in parallel,
+and in parallel lanes, then reports the throughput of each.";
+
+fn main() {
+    let count = match env::args().nth(1).map(|n| n.parse::<usize>()) {
+        None => 16_384,
+        Some(Ok(count)) => count,
+        Some(Err(err)) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            process::exit(1);
+        }
+    };
+
+    let keys = (0..count)
+        .map(|i| format!("flqrgnkx-{}", i))
+        .collect::<Vec<_>>();
+
+    let builder = KnotHashBuilder::default();
+
+    let (expected, throughput) = Throughput::measure(|| {
+        keys.iter()
+            .map(|key| builder.hash(key.as_bytes()))
+            .collect::<Vec<Digest>>()
+    });
+    println!("{:<10}{}", "single", throughput);
+
+    let (digests, throughput) = Throughput::measure(|| builder.hash_all(&keys));
+    assert_eq!(digests, expected, "parallel digests differ");
+    println!("{:<10}{}", "parallel", throughput);
+
+    let (digests, throughput) = Throughput::measure(|| builder.hash_all_lanes(&keys));
+    assert_eq!(digests, expected, "lane digests differ");
+    println!("{:<10}{}", "lanes", throughput);
+}
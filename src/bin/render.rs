@@ -0,0 +1,174 @@
+extern crate aoc2017;
+
+use aoc2017::day14::Disk;
+use aoc2017::day21::{self, Day21};
+use aoc2017::day22::{self, Automaton, RuleTable};
+use aoc2017::input::Source;
+use aoc2017::render::{Format, Frames, Image, Palette};
+use aoc2017::solution::Solution;
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::process;
+
+const USAGE: &str = "Usage: render <14|21|22> [OPTIONS]
+
+Draws the grid of the day on stdout, from the embedded input by default.
+
+OPTIONS:
+    --input <path>        read the puzzle input from a file, or stdin with -
+    --part 1|2            the grid of part 1 (default) or part 2
+    --format <format>     pbm, pgm, ppm (default) or ascii
+    --steps <n>           number of iterations or bursts, those of the part by default
+    --regions             day 14: colour every region
+    --rules <path>        day 22: read the rule table of the virus from a file
+    --frames <dir>        days 21 and 22: write every step to <dir>
+    --every <n>           only write one frame out of n";
+
+#[derive(Debug)]
+struct Options {
+    day: u32,
+    input: Source,
+    part: u32,
+    format: Format,
+    steps: Option<usize>,
+    regions: bool,
+    rules: Option<String>,
+    frames: Option<String>,
+    every: usize,
+}
+
+impl Options {
+    fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, Box<dyn Error>> {
+        let day = match args.next() {
+            Some(day) => day.parse().map_err(|_| format!("Invalid day: {}", day))?,
+            None => return Err("Missing day".into()),
+        };
+
+        let mut options = Options {
+            day,
+            input: Source::Embedded,
+            part: 1,
+            format: Format::Ppm,
+            steps: None,
+            regions: false,
+            rules: None,
+            frames: None,
+            every: 1,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("Missing value for {}", arg));
+
+            match arg.as_str() {
+                "--input" => {
+                    let path = value()?;
+
+                    options.input = if path == "-" {
+                        Source::Stdin
+                    } else {
+                        Source::File(path.into())
+                    };
+                }
+                "--part" => {
+                    options.part = match value()?.as_str() {
+                        "1" => 1,
+                        "2" => 2,
+                        part => return Err(format!("Invalid part: {}", part).into()),
+                    }
+                }
+                "--format" => options.format = value()?.parse()?,
+                "--steps" => options.steps = Some(value()?.parse()?),
+                "--regions" => options.regions = true,
+                "--rules" => options.rules = Some(value()?),
+                "--frames" => options.frames = Some(value()?),
+                "--every" => options.every = value()?.parse()?,
+                _ => return Err(format!("Unknown option: {}", arg).into()),
+            }
+        }
+
+        Ok(options)
+    }
+
+    fn frames(&self, prefix: &str, palette: &Palette) -> Option<Frames> {
+        self.frames.as_ref().map(|dir| {
+            Frames::new(dir, prefix)
+                .format(self.format)
+                .palette(palette.clone())
+                .every(self.every)
+        })
+    }
+}
+
+fn render(options: &Options) -> Result<(Image, Palette), Box<dyn Error>> {
+    let input = options.input.load(options.day)?;
+    let input = input.trim();
+
+    match options.day {
+        14 => {
+            let disk = Disk::new(input);
+
+            Ok(if options.regions {
+                let regions = disk.regions();
+                (regions.image(), Palette::distinct(regions.len()))
+            } else {
+                (disk.image(), Palette::default())
+            })
+        }
+        21 => {
+            let rules = Day21::parse(input)?;
+            let iterations = options
+                .steps
+                .unwrap_or(if options.part == 1 { 5 } else { 18 });
+
+            let palette = Palette::default();
+            let mut frames = options.frames("day21", &palette);
+
+            Ok((day21::render(&rules, iterations, frames.as_mut())?, palette))
+        }
+        22 => {
+            let bursts = options.steps.unwrap_or(if options.part == 1 {
+                10_000
+            } else {
+                10_000_000
+            });
+
+            let rules = match options.rules {
+                Some(ref path) => fs::read_to_string(path)?.parse()?,
+                None if options.part == 2 => RuleTable::evolved(),
+                None => RuleTable::simple(),
+            };
+
+            let mut automaton = Automaton::new(&rules, &day22::parse_map(input))?;
+
+            let palette = rules.palette();
+            let mut frames = options.frames("day22", &palette);
+            let image = day22::render(&mut automaton, bursts, frames.as_mut())?;
+
+            Ok((image, palette))
+        }
+        day => Err(format!("Day {} has no grid to render", day).into()),
+    }
+}
+
+fn main() {
+    let options = Options::from_args(env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{}\n\n{}", err, USAGE);
+        process::exit(1);
+    });
+
+    let result = render(&options).and_then(|(image, palette)| {
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+
+        image.write(options.format, &palette, &mut out)?;
+        Ok(out.flush()?)
+    });
+
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
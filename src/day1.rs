@@ -1,3 +1,7 @@
+use solution::Solution;
+
+use std::error::Error;
+
 fn uncaptcha(input: &str, gap: usize) -> u32 {
     input
         .chars()
@@ -14,6 +18,28 @@ pub fn part2(input: &str) -> u32 {
     uncaptcha(input, input.len() / 2)
 }
 
+pub struct Day1;
+
+impl Solution for Day1 {
+    const DAY: u32 = 1;
+
+    type Input = String;
+    type Output1 = u32;
+    type Output2 = u32;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(input.trim().to_owned())
+    }
+
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input)
+    }
+
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,4 +1,7 @@
-use knot_hasher::KnotHasher;
+use knot_hasher::{knot_hash, KnotHasher};
+use solution::Solution;
+
+use std::error::Error;
 
 pub fn part1(input: &str) -> u32 {
     let mut hasher = KnotHasher::new(256);
@@ -12,10 +15,29 @@ pub fn part1(input: &str) -> u32 {
 }
 
 pub fn part2(input: &str) -> String {
-    KnotHasher::dense_hash_from_key(input)
-        .iter()
-        .map(|xor| format!("{:02x}", xor))
-        .collect()
+    knot_hash(input).to_string()
+}
+
+pub struct Day10;
+
+impl Solution for Day10 {
+    const DAY: u32 = 10;
+
+    type Input = String;
+    type Output1 = u32;
+    type Output2 = String;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(input.trim().to_owned())
+    }
+
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input)
+    }
+
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
+    }
 }
 
 #[cfg(test)]
@@ -1,128 +1,90 @@
-#[derive(Debug)]
-struct HexagonalGrid {
-    x: i32,
-    y: i32,
-}
-
-impl HexagonalGrid {
-    fn new() -> HexagonalGrid {
-        HexagonalGrid { x: 0, y: 0 }
-    }
+use hex::{self, Direction, Hex, simplify, walk};
+use solution::Solution;
 
-    fn step(&mut self, direction: &HexagonalDirection) {
-        let (x, y) = direction.to_point();
-        self.x += x;
-        self.y += y;
-    }
+use std::error::Error;
 
-    fn distance(&self) -> u32 {
-        let z = -self.x - self.y;
+pub fn part1(path: &[Direction]) -> u32 {
+    walk(path).length()
+}
 
-        self.x.abs().max(self.y.abs()).max(z.abs()) as u32
-    }
+pub fn part2(path: &[Direction]) -> u32 {
+    path.iter()
+        .scan(Hex::ORIGIN, |hex, &direction| {
+            *hex = hex.step(direction);
+            Some(hex.length())
+        })
+        .max()
+        .unwrap_or(0)
 }
 
-#[derive(Debug, Eq, PartialEq)]
-enum HexagonalDirection {
-    North,
-    NorthEast,
-    SouthEast,
-    South,
-    SouthWest,
-    NorthWest,
+/// The moves of a shortest path to where the child process is.
+pub fn shortest_path(path: &[Direction]) -> Vec<Direction> {
+    simplify(path)
 }
 
-impl HexagonalDirection {
-    fn from_str(dir: &str) -> HexagonalDirection {
-        use self::HexagonalDirection::*;
-        match dir {
-            "n" => North,
-            "ne" => NorthEast,
-            "se" => SouthEast,
-            "s" => South,
-            "sw" => SouthWest,
-            "nw" => NorthWest,
-            _ => unreachable!(),
-        }
-    }
+pub struct Day11;
 
-    fn to_point(&self) -> (i32, i32) {
-        use self::HexagonalDirection::*;
-        match *self {
-            North => (0, -1),
-            NorthEast => (1, -1),
-            SouthEast => (1, 0),
-            South => (0, 1),
-            SouthWest => (-1, 1),
-            NorthWest => (-1, 0),
-        }
-    }
-}
+impl Solution for Day11 {
+    const DAY: u32 = 11;
 
-pub fn part1(input: &str) -> u32 {
-    let mut grid = HexagonalGrid::new();
-    for dir in input.split(',').map(HexagonalDirection::from_str) {
-        grid.step(&dir);
-    }
+    type Input = Vec<Direction>;
+    type Output1 = u32;
+    type Output2 = u32;
 
-    grid.distance()
-}
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(hex::parse_path(input.trim())?)
+    }
 
-pub fn part2(input: &str) -> u32 {
-    let mut grid = HexagonalGrid::new();
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input)
+    }
 
-    input
-        .split(',')
-        .map(HexagonalDirection::from_str)
-        .map(|dir| {
-            grid.step(&dir);
-            grid.distance()
-        })
-        .max()
-        .expect("Failed to compute max distance")
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn path(s: &str) -> Vec<Direction> {
+        hex::parse_path(s).unwrap()
+    }
+
     #[test]
     /// ne,ne,ne is 3 steps away.
     fn part1_sample1() {
-        let mut grid = HexagonalGrid::new();
-        for dir in &["ne", "ne", "ne"] {
-            grid.step(&HexagonalDirection::from_str(dir));
-        }
-        assert_eq!(grid.distance(), 3);
+        assert_eq!(part1(&path("ne,ne,ne")), 3);
     }
 
     #[test]
     /// ne,ne,sw,sw is 0 steps away (back where you started).
     fn part1_sample2() {
-        let mut grid = HexagonalGrid::new();
-        for dir in &["ne", "ne", "sw", "sw"] {
-            grid.step(&HexagonalDirection::from_str(dir));
-        }
-        assert_eq!(grid.distance(), 0);
+        assert_eq!(part1(&path("ne,ne,sw,sw")), 0);
     }
 
     #[test]
     /// ne,ne,s,s is 2 steps away (se,se).
     fn part1_sample3() {
-        let mut grid = HexagonalGrid::new();
-        for dir in &["ne", "ne", "s", "s"] {
-            grid.step(&HexagonalDirection::from_str(dir));
-        }
-        assert_eq!(grid.distance(), 2);
+        assert_eq!(part1(&path("ne,ne,s,s")), 2);
+        assert_eq!(shortest_path(&path("ne,ne,s,s")), path("se,se"));
     }
 
     #[test]
     /// se,sw,se,sw,sw is 3 steps away (s,s,sw).
     fn part1_sample4() {
-        let mut grid = HexagonalGrid::new();
-        for dir in &["se", "sw", "se", "sw", "sw"] {
-            grid.step(&HexagonalDirection::from_str(dir));
-        }
-        assert_eq!(grid.distance(), 3);
+        assert_eq!(part1(&path("se,sw,se,sw,sw")), 3);
+        assert_eq!(shortest_path(&path("se,sw,se,sw,sw")), path("s,s,sw"));
+    }
+
+    #[test]
+    fn part2_sample() {
+        assert_eq!(part2(&path("ne,ne,sw,sw")), 2);
+    }
+
+    #[test]
+    fn invalid_direction() {
+        assert!(Day11::parse("ne,up,s").is_err());
     }
 }
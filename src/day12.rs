@@ -1,13 +1,13 @@
 use day12_parser::parse;
+use solution::Solution;
 
 use std::collections::{HashMap, HashSet};
+use std::error::Error;
 
-pub fn part1(input: &str) -> u32 {
-    let input = parse(input);
-
+pub fn part1(input: &HashMap<u32, Vec<u32>>) -> u32 {
     let mut g = HashSet::new();
 
-    group(0, &input, &mut g);
+    group(0, input, &mut g);
 
     g.len() as u32
 }
@@ -20,8 +20,8 @@ fn group(item: u32, nodes: &HashMap<u32, Vec<u32>>, g: &mut HashSet<u32>) {
     }
 }
 
-pub fn part2(input: &str) -> u32 {
-    let mut input = parse(input);
+pub fn part2(input: &HashMap<u32, Vec<u32>>) -> u32 {
+    let mut input = input.clone();
 
     let mut i = 0;
     while !input.is_empty() {
@@ -39,6 +39,28 @@ pub fn part2(input: &str) -> u32 {
     i
 }
 
+pub struct Day12;
+
+impl Solution for Day12 {
+    const DAY: u32 = 12;
+
+    type Input = HashMap<u32, Vec<u32>>;
+    type Output1 = u32;
+    type Output2 = u32;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(parse(input.trim())?)
+    }
+
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input)
+    }
+
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -53,11 +75,11 @@ mod tests {
 
     #[test]
     fn part1_sample() {
-        assert_eq!(part1(INPUT), 6)
+        assert_eq!(part1(&parse(INPUT).unwrap()), 6)
     }
 
     #[test]
     fn part2_sample() {
-        assert_eq!(part2(INPUT), 2)
+        assert_eq!(part2(&parse(INPUT).unwrap()), 2)
     }
 }
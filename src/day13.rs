@@ -1,3 +1,7 @@
+use solution::Solution;
+
+use std::error::Error;
+
 #[derive(Debug, Eq, PartialEq)]
 struct Layer {
     depth: u32,
@@ -19,7 +23,7 @@ impl Layer {
     }
 
     fn collide(&self, start_time: u32) -> bool {
-        (start_time + self.depth) % self.period() == 0
+        (start_time + self.depth).is_multiple_of(self.period())
     }
 }
 
@@ -55,10 +59,29 @@ pub fn part1(input: &str) -> u32 {
 pub fn part2(input: &str) -> u32 {
     let firewall = Firewall::new(input.lines().map(Layer::from_str).collect());
 
-    (0..)
-        .filter_map(|i| if !firewall.collide(i) { Some(i) } else { None })
-        .next()
-        .unwrap()
+    (0..).find(|&i| !firewall.collide(i)).unwrap()
+}
+
+pub struct Day13;
+
+impl Solution for Day13 {
+    const DAY: u32 = 13;
+
+    type Input = String;
+    type Output1 = u32;
+    type Output2 = u32;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(input.trim().to_owned())
+    }
+
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input)
+    }
+
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
+    }
 }
 
 #[cfg(test)]
@@ -1,100 +1,277 @@
-use knot_hasher::KnotHasher;
-use rayon::prelude::*;
+use knot_hasher::KnotHashBuilder;
+use render::Image;
+use solution::Solution;
 
+use std::error::Error;
+
+/// The squares of the disk, one row per knot hash.
 #[derive(Debug, Eq, PartialEq)]
-struct Disk {
+pub struct Disk {
     rows: Vec<Vec<bool>>,
 }
 
 impl Disk {
-    fn new(key: &str) -> Disk {
-        let rows = (0..128)
-            .into_par_iter()
-            .map(|i| format!("{}-{}", key, i))
-            .map(|k| {
-                KnotHasher::dense_hash_from_key(&k)
-                    .iter()
-                    .map(|h| {
-                        [
-                            h & 0b1000_0000 > 0,
-                            h & 0b0100_0000 > 0,
-                            h & 0b0010_0000 > 0,
-                            h & 0b0001_0000 > 0,
-                            h & 0b0000_1000 > 0,
-                            h & 0b0000_0100 > 0,
-                            h & 0b0000_0010 > 0,
-                            h & 0b0000_0001 > 0,
-                        ]
-                    })
-                    .fold(Vec::with_capacity(128), |mut v, h| {
-                        v.extend_from_slice(&h);
-                        v
-                    })
+    /// The 128 rows of the puzzle.
+    pub fn new(key: &str) -> Disk {
+        Disk::with_rows(key, 128)
+    }
+
+    /// `rows` rows of 128 squares, row `i` being the knot hash of `key-i`.
+    pub fn with_rows(key: &str, rows: usize) -> Disk {
+        // Characters are truncated to bytes, as `knot_hash` does.
+        let keys = (0..rows)
+            .map(|i| {
+                format!("{}-{}", key, i)
+                    .chars()
+                    .map(|c| c as u8)
+                    .collect::<Vec<_>>()
             })
+            .collect::<Vec<_>>();
+
+        let rows = KnotHashBuilder::default()
+            .hash_all(&keys)
+            .iter()
+            .map(|digest| digest.bits().collect())
             .collect();
 
         Disk { rows }
     }
 
-    fn find_group(&self) -> Option<(usize, usize)> {
+    /// A disk of any dimensions.
+    ///
+    /// # Panics
+    ///
+    /// If the rows don't have the same length.
+    pub fn from_rows(rows: Vec<Vec<bool>>) -> Disk {
+        assert!(
+            rows.windows(2).all(|w| w[0].len() == w[1].len()),
+            "rows of a disk must have the same length"
+        );
+
+        Disk { rows }
+    }
+
+    pub fn rows(&self) -> &[Vec<bool>] {
+        &self.rows
+    }
+
+    pub fn width(&self) -> usize {
+        self.rows.first().map_or(0, |row| row.len())
+    }
+
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn used(&self) -> usize {
         self.rows
             .iter()
-            .enumerate()
-            .filter_map(|(r, row)| {
-                row.iter()
-                    .enumerate()
-                    .find(|&(_, &cell)| cell)
-                    .map(|(c, _)| (r, c))
-            })
-            .next()
+            .map(|row| row.iter().filter(|&&used| used).count())
+            .sum()
+    }
+
+    /// Index 1 for used squares.
+    pub fn image(&self) -> Image {
+        Image::from_rows(&self.rows)
     }
 
-    fn delete_group(&mut self, row: usize, column: usize) {
-        self.rows[row][column] = false;
+    /// Labels the regions of used squares, in a single pass over the disk.
+    pub fn regions(&self) -> Regions {
+        let width = self.width();
+        let index = |row: usize, column: usize| row * width + column;
 
-        if row > 0 && self.rows[row - 1][column] {
-            self.delete_group(row - 1, column);
+        let mut sets = UnionFind::new(width * self.height());
+
+        for (r, row) in self.rows.iter().enumerate() {
+            for (c, _) in row.iter().enumerate().filter(|&(_, &used)| used) {
+                if r > 0 && self.rows[r - 1][c] {
+                    sets.union(index(r - 1, c), index(r, c));
+                }
+
+                if c > 0 && row[c - 1] {
+                    sets.union(index(r, c - 1), index(r, c));
+                }
+            }
         }
 
-        if column > 0 && self.rows[row][column - 1] {
-            self.delete_group(row, column - 1);
+        let mut ids = vec![None; sets.parent.len()];
+        let mut labels = vec![None; sets.parent.len()];
+        let mut regions = Vec::<Region>::new();
+
+        for (r, row) in self.rows.iter().enumerate() {
+            for (c, _) in row.iter().enumerate().filter(|&(_, &used)| used) {
+                let root = sets.find(index(r, c));
+
+                let id = *ids[root].get_or_insert_with(|| {
+                    regions.push(Region {
+                        size: 0,
+                        bounds: Bounds {
+                            top: r,
+                            left: c,
+                            bottom: r,
+                            right: c,
+                        },
+                    });
+                    regions.len() - 1
+                });
+
+                let region = &mut regions[id];
+                region.size += 1;
+                region.bounds.left = region.bounds.left.min(c);
+                region.bounds.right = region.bounds.right.max(c);
+                region.bounds.bottom = r;
+
+                labels[index(r, c)] = Some(id);
+            }
+        }
+
+        Regions {
+            width,
+            labels,
+            regions,
+        }
+    }
+}
+
+/// Disjoint sets of squares, merged as neighbours are found.
+struct UnionFind {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> UnionFind {
+        UnionFind {
+            parent: (0..len).collect(),
+            rank: vec![0; len],
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
         }
 
-        if row < self.rows.len() - 1 && self.rows[row + 1][column] {
-            self.delete_group(row + 1, column);
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+
+        if a == b {
+            return;
         }
 
-        if let Some(&true) = self.rows[row].get(column + 1) {
-            self.delete_group(row, column + 1);
+        if self.rank[a] < self.rank[b] {
+            self.parent[a] = b;
+        } else {
+            self.parent[b] = a;
+
+            if self.rank[a] == self.rank[b] {
+                self.rank[a] += 1;
+            }
+        }
+    }
+}
+
+/// The smallest rectangle holding a region, bounds included.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Bounds {
+    pub top: usize,
+    pub left: usize,
+    pub bottom: usize,
+    pub right: usize,
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Region {
+    /// Number of squares.
+    pub size: usize,
+    pub bounds: Bounds,
+}
+
+/// The regions of a disk, numbered in the order their first square is met
+/// reading the disk row by row.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Regions {
+    width: usize,
+    labels: Vec<Option<usize>>,
+    regions: Vec<Region>,
+}
+
+impl Regions {
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// The id of the region holding a square, `None` for a free square.
+    pub fn label(&self, row: usize, column: usize) -> Option<usize> {
+        if column < self.width {
+            self.labels.get(row * self.width + column).and_then(|&id| id)
+        } else {
+            None
         }
     }
+
+    pub fn get(&self, id: usize) -> Option<&Region> {
+        self.regions.get(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Region> {
+        self.regions.iter()
+    }
+
+    /// Index `id + 1` for the squares of region `id`, to be drawn with
+    /// `Palette::distinct(regions.len())`.
+    pub fn image(&self) -> Image {
+        let height = self.labels.len() / self.width.max(1);
+
+        Image::from_fn(self.width, height, |x, y| {
+            self.label(y, x).map_or(0, |id| id + 1)
+        })
+    }
 }
 
-pub fn part1(input: &str) -> u32 {
-    let disk = Disk::new(input);
+pub fn part1(input: &str) -> usize {
+    Disk::new(input).used()
+}
 
-    disk.rows
-        .iter()
-        .map(|r| r.iter().map(|&b| if b { 1 } else { 0 }).sum::<u32>())
-        .sum()
+pub fn part2(input: &str) -> usize {
+    Disk::new(input).regions().len()
 }
 
-pub fn part2(input: &str) -> u32 {
-    let mut disk = Disk::new(input);
-    let mut nb_group = 0;
+pub struct Day14;
+
+impl Solution for Day14 {
+    const DAY: u32 = 14;
 
-    while let Some((r, c)) = disk.find_group() {
-        nb_group += 1;
+    type Input = String;
+    type Output1 = usize;
+    type Output2 = usize;
 
-        disk.delete_group(r, c);
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(input.trim().to_owned())
     }
 
-    nb_group
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input)
+    }
+
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use knot_hasher::knot_hash;
+    use render::Palette;
 
     #[test]
     fn sample_disk() {
@@ -110,6 +287,16 @@ mod tests {
         assert_eq!(disk.rows[7][0..8], from_sample("##.#.##.")[..]);
     }
 
+    #[test]
+    fn non_ascii_key() {
+        let disk = Disk::with_rows("\u{e9}t\u{e9}", 2);
+
+        for (i, row) in disk.rows.iter().enumerate() {
+            let digest = knot_hash(&format!("\u{e9}t\u{e9}-{}", i));
+            assert_eq!(*row, digest.bits().collect::<Vec<_>>());
+        }
+    }
+
     #[test]
     fn part1_sample() {
         assert_eq!(part1("flqrgnkx"), 8108);
@@ -120,6 +307,85 @@ mod tests {
         assert_eq!(part2("flqrgnkx"), 1242);
     }
 
+    #[test]
+    fn sample_regions() {
+        let regions = Disk::new("flqrgnkx").regions();
+
+        // ##.#.#..
+        // .#.#.#.#
+        assert_eq!(regions.label(0, 0), Some(0));
+        assert_eq!(regions.label(0, 1), Some(0));
+        assert_eq!(regions.label(1, 1), Some(0));
+        assert_eq!(regions.label(0, 2), None);
+        assert_eq!(regions.label(0, 3), Some(1));
+        assert_eq!(regions.label(1, 3), Some(1));
+        assert_eq!(regions.label(0, 128), None);
+
+        assert_eq!(
+            regions.get(0),
+            Some(&Region {
+                size: 3,
+                bounds: Bounds {
+                    top: 0,
+                    left: 0,
+                    bottom: 1,
+                    right: 1,
+                },
+            })
+        );
+        assert_eq!(
+            regions.iter().map(|r| r.size).sum::<usize>(),
+            Disk::new("flqrgnkx").used()
+        );
+    }
+
+    #[test]
+    fn regions_of_any_shape() {
+        // #.###
+        // #.#.#
+        // ###.#
+        let disk = Disk::from_rows(vec![
+            from_sample("#.###"),
+            from_sample("#.#.#"),
+            from_sample("###.#"),
+        ]);
+        let regions = disk.regions();
+
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions.label(2, 4), Some(0));
+        assert_eq!(
+            regions.get(0).unwrap().bounds,
+            Bounds {
+                top: 0,
+                left: 0,
+                bottom: 2,
+                right: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn region_image() {
+        let disk = Disk::from_rows(vec![from_sample("##.#"), from_sample("...#")]);
+
+        assert_eq!(
+            disk.regions().image().to_ascii(&Palette::distinct(2)),
+            "AA.B\n...B\n"
+        );
+        assert_eq!(disk.image().to_ascii(&Palette::default()), "##.#\n...#\n");
+    }
+
+    #[test]
+    fn large_disk() {
+        let disk = Disk::from_rows(vec![vec![true; 2000]; 2000]);
+        let regions = disk.regions();
+
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions.get(0).unwrap().size, 4_000_000);
+
+        assert_eq!(Disk::with_rows("flqrgnkx", 300).height(), 300);
+    }
+
     fn from_sample(sample: &str) -> Vec<bool> {
         sample
             .chars()
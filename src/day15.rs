@@ -1,49 +1,321 @@
-#[derive(Debug)]
-struct Generator {
+use parse_error::{ErrorKind, ParseError};
+use rayon::prelude::*;
+use solution::Solution;
+
+use std::error::Error;
+
+/// The modulus of the puzzle, `2^31 - 1`.
+pub const MODULUS: u64 = 2_147_483_647;
+
+/// Values multiplied by `factor` modulo `modulus`, at every step.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Generator {
     factor: u64,
-    previous_value: u64,
+    modulus: u64,
+    value: u64,
 }
 
 impl Generator {
-    fn new(factor: u64, initial: u64) -> Generator {
+    pub fn new(factor: u64, initial: u64) -> Generator {
+        Generator::with_modulus(factor, MODULUS, initial)
+    }
+
+    /// # Panics
+    ///
+    /// If `modulus` is 0.
+    pub fn with_modulus(factor: u64, modulus: u64, initial: u64) -> Generator {
+        assert!(modulus > 0, "a generator needs a non-zero modulus");
+
         Generator {
-            factor,
-            previous_value: initial,
+            factor: factor % modulus,
+            modulus,
+            value: initial % modulus,
+        }
+    }
+
+    /// The last value generated, or the initial one.
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    fn mul(&self, a: u64, b: u64) -> u64 {
+        if self.modulus == MODULUS {
+            // Both are below 2^31: fold the high bits back, as 2^31 = 1.
+            let product = a * b;
+            let folded = (product & MODULUS) + (product >> 31);
+
+            if folded >= MODULUS {
+                folded - MODULUS
+            } else {
+                folded
+            }
+        } else {
+            (u128::from(a) * u128::from(b) % u128::from(self.modulus)) as u64
         }
     }
+
+    /// Skips `steps` values at once, multiplying by `factor^steps`.
+    pub fn jump(&mut self, mut steps: u64) {
+        let mut factor = self.factor;
+
+        while steps > 0 {
+            if steps & 1 == 1 {
+                self.value = self.mul(self.value, factor);
+            }
+
+            factor = self.mul(factor, factor);
+            steps >>= 1;
+        }
+    }
+
+    /// The generator `steps` values ahead.
+    pub fn jumped(mut self, steps: u64) -> Generator {
+        self.jump(steps);
+        self
+    }
 }
 
 impl Iterator for Generator {
-    type Item = u16;
+    type Item = u64;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.previous_value = self.previous_value * self.factor % 2_147_483_647;
+        self.value = self.mul(self.value, self.factor);
 
-        Some(self.previous_value as u16)
+        Some(self.value)
     }
 }
 
-pub fn part1(a: u64, b: u64) -> usize {
-    let gen_a = Generator::new(16_807, a);
-    let gen_b = Generator::new(48_271, b);
+/// Number of values each parallel task generates.
+const CHUNK: u64 = 1 << 20;
+
+/// Most chunks searched at once for accepted values.
+const ROUND: u64 = 64;
+
+/// Compares the low bits of two generators' values.
+#[derive(Debug, Copy, Clone)]
+pub struct Judge {
+    a: Generator,
+    b: Generator,
+    mask: u64,
+}
+
+impl Judge {
+    /// A judge of the 16 lowest bits.
+    pub fn new(a: Generator, b: Generator) -> Judge {
+        Judge {
+            a,
+            b,
+            mask: 0xffff,
+        }
+    }
+
+    /// Compares the `bits` lowest bits instead, up to 64.
+    pub fn bits(mut self, bits: u32) -> Judge {
+        self.mask = if bits >= 64 { !0 } else { (1 << bits) - 1 };
+        self
+    }
+
+    /// Number of matches in the next `pairs` pairs, in parallel chunks.
+    pub fn count(&self, pairs: u64) -> usize {
+        let chunks = pairs.div_ceil(CHUNK);
+
+        (0..chunks)
+            .into_par_iter()
+            .map(|chunk| {
+                let start = chunk * CHUNK;
+                let len = CHUNK.min(pairs - start);
+
+                self.a
+                    .jumped(start)
+                    .zip(self.b.jumped(start))
+                    .take(len as usize)
+                    .filter(|&(a, b)| (a ^ b) & self.mask == 0)
+                    .count()
+            })
+            .sum()
+    }
+
+    /// Number of matches in the next `pairs` pairs, generator A only giving
+    /// multiples of `multiple_a` and generator B multiples of `multiple_b`.
+    ///
+    /// The accepted values are found in parallel chunks, a window of pairs at a
+    /// time. `None` if a generator never gives a multiple of its `multiple`.
+    ///
+    /// # Panics
+    ///
+    /// If either multiple is 0.
+    pub fn count_picky(&self, pairs: u64, multiple_a: u64, multiple_b: u64) -> Option<usize> {
+        assert!(
+            multiple_a > 0 && multiple_b > 0,
+            "generators can only be picky about non-zero multiples"
+        );
+
+        let (mut a, mut b) = (self.a, self.b);
+        let mut matches = 0;
+        let mut remaining = pairs;
 
-    gen_a
-        .zip(gen_b)
-        .take(40_000_000)
-        .filter(|&(a, b)| a == b)
-        .count()
+        while remaining > 0 {
+            let window = remaining.min(CHUNK * 4);
+
+            let accepted_a = accepted(&mut a, multiple_a, self.mask, window)?;
+            let accepted_b = accepted(&mut b, multiple_b, self.mask, window)?;
+
+            matches += accepted_a
+                .iter()
+                .zip(&accepted_b)
+                .filter(|&(a, b)| a == b)
+                .count();
+
+            remaining -= window;
+        }
+
+        Some(matches)
+    }
+}
+
+/// The masked values of the next `n` multiples of `multiple`, `generator`
+/// being left on the last one.
+///
+/// A generator has at most `modulus` different values, so once it went that
+/// many steps without giving a multiple, it never will: `None`.
+fn accepted(generator: &mut Generator, multiple: u64, mask: u64, n: u64) -> Option<Vec<u64>> {
+    let is_accepted = |value: u64| {
+        if multiple.is_power_of_two() {
+            value & (multiple - 1) == 0
+        } else {
+            value.is_multiple_of(multiple)
+        }
+    };
+
+    let mut values = Vec::with_capacity(n as usize);
+    // Steps since the last multiple, at least.
+    let mut dry = 0;
+
+    while (values.len() as u64) < n {
+        let missing = n - values.len() as u64;
+        let chunks = missing.saturating_mul(multiple).div_ceil(CHUNK).min(ROUND);
+        let start = *generator;
+
+        let found = (0..chunks)
+            .into_par_iter()
+            .map(|chunk| {
+                start
+                    .jumped(chunk * CHUNK)
+                    .take(CHUNK as usize)
+                    .filter(|&value| is_accepted(value))
+                    .map(|value| value & mask)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut steps = chunks * CHUNK;
+
+        if found.iter().all(Vec::is_empty) {
+            dry += steps;
+
+            if dry >= generator.modulus {
+                return None;
+            }
+        } else {
+            dry = 0;
+        }
+
+        for (chunk, chunk_values) in found.into_iter().enumerate() {
+            let wanted = (n - values.len() as u64) as usize;
+
+            if chunk_values.len() < wanted {
+                values.extend(chunk_values);
+                continue;
+            }
+
+            values.extend_from_slice(&chunk_values[..wanted]);
+
+            // Stop right after the last value kept.
+            let last = start
+                .jumped(chunk as u64 * CHUNK)
+                .enumerate()
+                .filter(|&(_, value)| is_accepted(value))
+                .nth(wanted - 1)
+                .map_or(0, |(i, _)| i as u64 + 1);
+
+            steps = chunk as u64 * CHUNK + last;
+            break;
+        }
+
+        generator.jump(steps);
+    }
+
+    Some(values)
+}
+
+/// The starting values of generators A and B, from their two lines.
+fn parse_input(input: &str) -> Result<(u64, u64), ParseError> {
+    let mut lines = input.lines();
+
+    let mut start = |line: usize, name: char| {
+        let text = lines
+            .next()
+            .ok_or_else(|| ParseError::new(line, 1, "", ErrorKind::Incomplete))?;
+        let prefix = format!("Generator {} starts with ", name);
+
+        if !text.starts_with(&prefix) {
+            let column = text
+                .bytes()
+                .zip(prefix.bytes())
+                .take_while(|&(a, b)| a == b)
+                .count();
+
+            return Err(ParseError::new(line, column + 1, text, ErrorKind::Syntax));
+        }
+
+        text[prefix.len()..].parse().map_err(|_| {
+            ParseError::new(
+                line,
+                prefix.len() + 1,
+                text,
+                ErrorKind::Invalid("expected a number".to_owned()),
+            )
+        })
+    };
+
+    let (a, b) = (start(1, 'A')?, start(2, 'B')?);
+
+    match lines.next() {
+        Some(text) => Err(ParseError::new(3, 1, text, ErrorKind::TrailingInput)),
+        None => Ok((a, b)),
+    }
+}
+
+pub fn part1(a: u64, b: u64) -> usize {
+    Judge::new(Generator::new(16_807, a), Generator::new(48_271, b)).count(40_000_000)
 }
 
 pub fn part2(a: u64, b: u64) -> usize {
-    let gen_a = Generator::new(16_807, a);
-    let gen_b = Generator::new(48_271, b);
+    Judge::new(Generator::new(16_807, a), Generator::new(48_271, b))
+        .count_picky(5_000_000, 4, 8)
+        .expect("the puzzle's generators go through every value")
+}
+
+pub struct Day15;
+
+impl Solution for Day15 {
+    const DAY: u32 = 15;
 
-    gen_a
-        .filter(|&a| a % 4 == 0)
-        .zip(gen_b.filter(|&b| b % 8 == 0))
-        .take(5_000_000)
-        .filter(|&(a, b)| a == b)
-        .count()
+    type Input = (u64, u64);
+    type Output1 = usize;
+    type Output2 = usize;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(parse_input(input.trim())?)
+    }
+
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input.0, input.1)
+    }
+
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input.0, input.1)
+    }
 }
 
 #[cfg(test)]
@@ -59,4 +331,108 @@ mod tests {
     fn part2_sample() {
         assert_eq!(part2(65, 8_921), 309);
     }
+
+    #[test]
+    fn sample_values() {
+        let a = Generator::new(16_807, 65);
+
+        assert_eq!(
+            a.take(5).collect::<Vec<_>>(),
+            vec![1_092_455, 1_181_022_009, 245_556_042, 1_744_312_007, 1_352_636_452]
+        );
+        assert_eq!(a.jumped(5).value(), 1_352_636_452);
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            parse_input("Generator A starts with 65\nGenerator B starts with 8921"),
+            Ok((65, 8_921))
+        );
+        assert_eq!(
+            parse_input("Generator A starts with 5l6\nGenerator B starts with 190 999"),
+            Err(ParseError::new(
+                1,
+                25,
+                "Generator A starts with 5l6",
+                ErrorKind::Invalid("expected a number".to_owned())
+            ))
+        );
+        assert_eq!(
+            parse_input("Generator A starts with 65\nGenerator C starts with 8921")
+                .unwrap_err()
+                .column,
+            11
+        );
+        assert_eq!(
+            parse_input("Generator A starts with 65").unwrap_err().kind,
+            ErrorKind::Incomplete
+        );
+        assert_eq!(
+            parse_input("Generator A starts with 65\nGenerator B starts with 1\n3")
+                .unwrap_err()
+                .kind,
+            ErrorKind::TrailingInput
+        );
+    }
+
+    #[test]
+    fn jump_ahead() {
+        let mut stepped = Generator::with_modulus(48_271, 1_000_003, 8_921);
+        let jumped = stepped.jumped(12_345);
+
+        stepped.nth(12_344);
+        assert_eq!(stepped, jumped);
+
+        let mut big = Generator::with_modulus(3, (1 << 61) - 1, 7);
+        let jumped = big.jumped(100_000);
+        big.nth(99_999);
+        assert_eq!(big, jumped);
+
+        // 7 * 3^(2^40) mod 2^61 - 1
+        let far = Generator::with_modulus(3, (1 << 61) - 1, 7).jumped(1 << 40);
+        assert_eq!(far.value(), 1_001_541_935_778_136_729);
+    }
+
+    #[test]
+    fn other_judges() {
+        let a = Generator::with_modulus(16_807, 65_521, 65);
+        let b = Generator::with_modulus(48_271, 65_521, 8_921);
+
+        let serial = |bits: u32, pairs: usize| {
+            a.zip(b)
+                .take(pairs)
+                .filter(|&(a, b)| (a ^ b) % (1 << bits) == 0)
+                .count()
+        };
+
+        let judge = Judge::new(a, b).bits(4);
+        assert_eq!(judge.count(2_500_000), serial(4, 2_500_000));
+
+        let picky = a
+            .filter(|a| a % 3 == 0)
+            .zip(b.filter(|b| b % 5 == 0))
+            .take(500_000)
+            .filter(|&(a, b)| (a ^ b) % 16 == 0)
+            .count();
+        assert_eq!(judge.count_picky(500_000, 3, 5), Some(picky));
+    }
+
+    #[test]
+    fn never_picked() {
+        // 1, 2, 4, 1, ... is never a multiple of 8.
+        let a = Generator::with_modulus(2, 7, 1);
+        let b = Generator::new(48_271, 8_921);
+
+        assert_eq!(Judge::new(a, b).count_picky(10, 8, 1), None);
+        assert_eq!(Judge::new(b, a).count_picky(10, 1, u64::MAX), None);
+        assert_eq!(Judge::new(a, b).count_picky(3, 4, 1), Some(0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn picky_about_zero() {
+        let a = Generator::new(16_807, 65);
+        Judge::new(a, a).count_picky(10, 0, 1);
+    }
 }
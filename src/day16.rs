@@ -1,6 +1,26 @@
+use parse_error::{ErrorKind, ParseError};
+use solution::Solution;
+
+use std::error::Error;
+use std::fmt;
 use std::str::FromStr;
-use std::num::ParseIntError;
-use std::collections::HashMap;
+
+/// The name of the `i`th program: `a` to `z`, then the characters after `z`,
+/// for the first `MAX_DANCERS` programs.
+fn name(i: usize) -> char {
+    std::char::from_u32(u32::from(b'a') + i as u32).expect("too many programs to name")
+}
+
+/// Number of programs with a name, the surrogates not being characters.
+pub const MAX_DANCERS: usize = 0xd800 - b'a' as usize;
+
+/// The index of a program from its name.
+fn index(name: char) -> usize {
+    (name as u32 - u32::from(b'a')) as usize
+}
+
+/// Number of programs in the puzzle's dance.
+const NB_DANCERS: usize = 16;
 
 struct Dance {
     dancers: Vec<char>,
@@ -9,10 +29,8 @@ struct Dance {
 
 impl Dance {
     fn new(nb_dancers: usize) -> Dance {
-        assert!(nb_dancers <= 26);
-
         Dance {
-            dancers: (b'a'..).map(|c| c as char).take(nb_dancers).collect(),
+            dancers: (0..nb_dancers).map(name).collect(),
             offset: 0,
         }
     }
@@ -26,7 +44,7 @@ impl Dance {
     }
 
     fn spin(&mut self, offset: usize) {
-        let offset = self.dancers.len() - offset;
+        let offset = self.dancers.len() - offset % self.dancers.len();
         self.offset = (self.offset + offset) % self.dancers.len();
     }
 
@@ -43,172 +61,369 @@ impl Dance {
 
         self.dancers.swap(a, b);
     }
+}
 
-    fn to_string(&self) -> String {
+impl fmt::Display for Dance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.dancers[self.offset..]
             .iter()
             .chain(self.dancers[..self.offset].iter())
-            .collect()
+            .try_for_each(|d| write!(f, "{}", d))
     }
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
-enum Move {
+pub enum Move {
     Spin(usize),
     Exchange(usize, usize),
     Partner(char, char),
 }
 
-impl FromStr for Move {
-    type Err = ParseMoveError;
+impl Move {
+    /// Whether the move can be danced by `nb_dancers` programs.
+    fn fits(self, nb_dancers: usize) -> bool {
+        match self {
+            Move::Spin(_) => nb_dancers > 0,
+            Move::Exchange(a, b) => a < nb_dancers && b < nb_dancers,
+            Move::Partner(a, b) => index(a) < nb_dancers && index(b) < nb_dancers,
+        }
+    }
+}
 
-    fn from_str(m: &str) -> Result<Self, ParseMoveError> {
-        let (action, value) = m.split_at(1);
+#[derive(Debug, Eq, PartialEq)]
+pub enum DanceError {
+    /// More programs than `MAX_DANCERS`.
+    TooManyDancers(usize),
+    /// A move naming a position or a program beyond the dancers.
+    InvalidMove {
+        /// Index of the move in the routine.
+        index: usize,
+        nb_dancers: usize,
+    },
+}
 
-        match action {
-            "s" => Ok(Move::Spin(value.parse()?)),
-            "x" => {
-                let value = value.split('/').collect::<Vec<_>>();
+impl fmt::Display for DanceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DanceError::TooManyDancers(nb_dancers) => write!(
+                f,
+                "{} programs can't all be named, at most {} can",
+                nb_dancers, MAX_DANCERS
+            ),
+            DanceError::InvalidMove { index, nb_dancers } => write!(
+                f,
+                "move {} can't be danced by {} programs",
+                index + 1,
+                nb_dancers
+            ),
+        }
+    }
+}
+
+impl Error for DanceError {}
 
-                let a = value.get(0).ok_or(ParseMoveError::ActionError)?;
-                let b = value.get(1).ok_or(ParseMoveError::ActionError)?;
+fn check(nb_dancers: usize, moves: &[Move]) -> Result<(), DanceError> {
+    if nb_dancers > MAX_DANCERS {
+        return Err(DanceError::TooManyDancers(nb_dancers));
+    }
+
+    match moves.iter().position(|m| !m.fits(nb_dancers)) {
+        Some(index) => Err(DanceError::InvalidMove { index, nb_dancers }),
+        None => Ok(()),
+    }
+}
 
-                Ok(Move::Exchange(a.parse()?, b.parse()?))
+impl FromStr for Move {
+    type Err = ParseError;
+
+    /// Parses a single move, columns in errors being relative to the move.
+    fn from_str(m: &str) -> Result<Self, ParseError> {
+        let error = |column, kind| ParseError::new(1, column, m, kind);
+
+        let number = |value: &str, column| {
+            value
+                .parse()
+                .map_err(|_| error(column, ErrorKind::Invalid("expected a number".to_owned())))
+        };
+
+        // Any name `name` gives, whether the dance has that many programs
+        // being checked later.
+        let program = |value: &str, column| match (value.chars().next(), value.chars().nth(1)) {
+            (Some(c), None) if c >= 'a' => Ok(c),
+            _ => Err(error(
+                column,
+                ErrorKind::Invalid("expected a program name".to_owned()),
+            )),
+        };
+
+        let mut chars = m.chars();
+        let action = chars
+            .next()
+            .ok_or_else(|| error(1, ErrorKind::Incomplete))?;
+        let value = chars.as_str();
+
+        let pair = || {
+            value
+                .find('/')
+                .map(|i| ((&value[..i], 2), (&value[i + 1..], i + 3)))
+                .ok_or_else(|| error(m.len() + 1, ErrorKind::Incomplete))
+        };
+
+        match action {
+            's' => Ok(Move::Spin(number(value, 2)?)),
+            'x' => {
+                let ((a, col_a), (b, col_b)) = pair()?;
+
+                Ok(Move::Exchange(number(a, col_a)?, number(b, col_b)?))
             }
-            "p" => {
-                let value = value
-                    .split('/')
-                    .filter_map(|c| c.chars().next())
-                    .collect::<Vec<_>>();
-
-                Ok(Move::Partner(
-                    *value.get(0).ok_or(ParseMoveError::ActionError)?,
-                    *value.get(1).ok_or(ParseMoveError::ActionError)?,
-                ))
+            'p' => {
+                let ((a, col_a), (b, col_b)) = pair()?;
+
+                Ok(Move::Partner(program(a, col_a)?, program(b, col_b)?))
             }
-            _ => Err(ParseMoveError::ActionError),
+            _ => Err(error(1, ErrorKind::Syntax)),
         }
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
-enum ParseMoveError {
-    ActionError,
-    ParseError(ParseIntError),
+fn parse_input(input: &str) -> Result<Vec<Move>, ParseError> {
+    let mut offset = 0;
+
+    input
+        .split(',')
+        .map(|m| {
+            let column_offset = offset;
+            offset += m.len() + 1;
+
+            m.parse::<Move>()
+                .map_err(|err| err.shifted(1, column_offset, input))
+        })
+        .collect()
 }
 
-impl From<ParseIntError> for ParseMoveError {
-    fn from(err: ParseIntError) -> Self {
-        ParseMoveError::ParseError(err)
+/// A rearrangement of `len()` items, item `i` of the result being item
+/// `self[i]` of the input.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Permutation(Vec<usize>);
+
+impl Permutation {
+    pub fn identity(len: usize) -> Permutation {
+        Permutation((0..len).collect())
     }
-}
 
-#[derive(Debug)]
-struct FastDance {
-    dancers: Vec<char>,
-    dance: Vec<usize>,
-    permutations: HashMap<char, char>,
-}
+    /// # Panics
+    ///
+    /// If `indices` isn't a permutation of `0..indices.len()`.
+    pub fn from_indices(indices: Vec<usize>) -> Permutation {
+        let mut seen = vec![false; indices.len()];
+
+        for &i in &indices {
+            assert!(
+                i < seen.len() && !seen[i],
+                "not a permutation: {:?}",
+                indices
+            );
+            seen[i] = true;
+        }
 
-impl FastDance {
-    fn new(nb_dancers: usize, moves: &[Move]) -> FastDance {
-        let mut dance = Dance::new(nb_dancers);
+        Permutation(indices)
+    }
 
-        for m in moves.iter().filter_map(|&m| {
-            if let Move::Partner(_, _) = m {
-                None
-            } else {
-                Some(m)
-            }
-        }) {
-            dance.make_move(m);
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn indices(&self) -> &[usize] {
+        &self.0
+    }
+
+    pub fn apply<T: Clone>(&self, items: &[T]) -> Vec<T> {
+        self.0.iter().map(|&i| items[i].clone()).collect()
+    }
+
+    /// The permutation applying `self`, then `other`.
+    pub fn then(&self, other: &Permutation) -> Permutation {
+        assert_eq!(self.len(), other.len(), "permutations of different sizes");
+
+        Permutation(other.0.iter().map(|&i| self.0[i]).collect())
+    }
+
+    pub fn inverse(&self) -> Permutation {
+        let mut inverse = vec![0; self.len()];
+
+        for (i, &from) in self.0.iter().enumerate() {
+            inverse[from] = i;
         }
 
-        let mut permutations = (b'a'..)
-            .map(|c| c as char)
-            .map(|c| (c, c))
-            .take(nb_dancers)
-            .collect::<HashMap<_, _>>();
-
-        for (a, b) in moves.iter().filter_map(|&m| {
-            if let Move::Partner(a, b) = m {
-                Some((a, b))
-            } else {
-                None
+        Permutation(inverse)
+    }
+
+    /// `self` applied `n` times, by squaring.
+    pub fn pow(&self, mut n: u64) -> Permutation {
+        let mut result = Permutation::identity(self.len());
+        let mut square = self.clone();
+
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result.then(&square);
             }
-        }) {
-            let b_from = permutations[&b];
 
-            let a_from = permutations.insert(a, b_from).unwrap();
-            permutations.insert(b, a_from);
+            square = square.then(&square);
+            n >>= 1;
         }
 
-        permutations = permutations.into_iter().map(|(k, v)| (v, k)).collect();
+        result
+    }
+
+    /// The cycles of length 2 or more, each one listing the positions an item
+    /// goes through, starting from the smallest.
+    pub fn cycles(&self) -> Vec<Vec<usize>> {
+        let inverse = self.inverse();
+        let mut seen = vec![false; self.len()];
+        let mut cycles = Vec::new();
+
+        for start in 0..self.len() {
+            if seen[start] {
+                continue;
+            }
+
+            let mut cycle = vec![start];
+            seen[start] = true;
+
+            let mut i = inverse.0[start];
+            while i != start {
+                cycle.push(i);
+                seen[i] = true;
+                i = inverse.0[i];
+            }
 
-        FastDance {
-            dancers: (b'a'..).map(|c| c as char).take(nb_dancers).collect(),
-            dance: dance
-                .to_string()
-                .chars()
-                .map(|c| (c as u8 - b'a') as usize)
-                .collect(),
-            permutations,
+            if cycle.len() > 1 {
+                cycles.push(cycle);
+            }
         }
+
+        cycles
     }
+}
 
-    fn dance(&mut self) {
-        self.dancers = self.dance
-            .iter()
-            .map(|&i| self.dancers[i])
-            .map(|c| self.permutations[&c])
-            .collect();
+/// A list of moves as two permutations: spins and exchanges move positions,
+/// partners rename programs wherever they stand, so the two commute.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Routine {
+    positions: Permutation,
+    /// Program `i` is renamed `names[i]`.
+    names: Permutation,
+}
+
+impl Routine {
+    pub fn new(nb_dancers: usize, moves: &[Move]) -> Result<Routine, DanceError> {
+        check(nb_dancers, moves)?;
+
+        let mut positions = (0..nb_dancers).collect::<Vec<_>>();
+        let mut names = (0..nb_dancers).collect::<Vec<_>>();
+
+        for &m in moves {
+            match m {
+                Move::Spin(offset) => positions.rotate_right(offset % nb_dancers),
+                Move::Exchange(a, b) => positions.swap(a, b),
+                Move::Partner(a, b) => {
+                    let a = names.iter().position(|&n| n == index(a)).unwrap();
+                    let b = names.iter().position(|&n| n == index(b)).unwrap();
+
+                    names.swap(a, b);
+                }
+            }
+        }
+
+        Ok(Routine {
+            positions: Permutation(positions),
+            names: Permutation(names),
+        })
     }
 
-    fn to_string(&self) -> String {
-        self.dancers.iter().collect()
+    pub fn positions(&self) -> &Permutation {
+        &self.positions
     }
 
-    fn get_cycle(&mut self) -> Vec<String> {
-        let mut cycle = vec![self.to_string()];
+    pub fn names(&self) -> &Permutation {
+        &self.names
+    }
 
-        for _ in 0.. {
-            self.dance();
-            let positions = self.to_string();
+    /// The routine dancing `self`, then `other`.
+    pub fn then(&self, other: &Routine) -> Routine {
+        Routine {
+            positions: self.positions.then(&other.positions),
+            names: other.names.then(&self.names),
+        }
+    }
 
-            if cycle.contains(&positions) {
-                break;
-            }
+    pub fn inverse(&self) -> Routine {
+        Routine {
+            positions: self.positions.inverse(),
+            names: self.names.inverse(),
+        }
+    }
 
-            cycle.push(positions);
+    pub fn pow(&self, n: u64) -> Routine {
+        Routine {
+            positions: self.positions.pow(n),
+            names: self.names.pow(n),
         }
+    }
 
-        cycle
+    /// The line of programs after the routine, starting from `a`, `b`, ...
+    pub fn lineup(&self) -> String {
+        self.positions
+            .indices()
+            .iter()
+            .map(|&i| name(self.names.indices()[i]))
+            .collect()
     }
 }
 
-pub fn part1(input: &str) -> String {
-    let mut dance = Dance::new(16);
+pub fn part1(input: &[Move]) -> Result<String, DanceError> {
+    check(NB_DANCERS, input)?;
+
+    let mut dance = Dance::new(NB_DANCERS);
 
-    for m in input.split(',').filter_map(|m| m.parse::<Move>().ok()) {
+    for &m in input {
         dance.make_move(m);
     }
 
-    dance.to_string()
+    Ok(dance.to_string())
 }
 
-pub fn part2(input: &str) -> String {
-    let moves = input
-        .split(',')
-        .filter_map(|m| m.parse::<Move>().ok())
-        .collect::<Vec<_>>();
+pub fn part2(input: &[Move]) -> Result<String, DanceError> {
+    Ok(Routine::new(NB_DANCERS, input)?.pow(1_000_000_000).lineup())
+}
+
+pub struct Day16;
+
+impl Solution for Day16 {
+    const DAY: u32 = 16;
+
+    type Input = Vec<Move>;
+    type Output1 = String;
+    type Output2 = String;
 
-    let mut fast_dance = FastDance::new(16, &moves);
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        let moves = parse_input(input.trim())?;
+        check(NB_DANCERS, &moves)?;
 
-    let mut cycle = fast_dance.get_cycle();
+        Ok(moves)
+    }
 
-    let index = 1_000_000_000 % cycle.len();
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input).expect("moves are checked when parsed")
+    }
 
-    cycle.remove(index)
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input).expect("moves are checked when parsed")
+    }
 }
 
 #[cfg(test)]
@@ -230,6 +445,62 @@ mod tests {
         assert_eq!("pe/b".parse(), Ok(Move::Partner('e', 'b')));
     }
 
+    #[test]
+    fn parse_partner_after_z() {
+        assert_eq!("p{/~".parse(), Ok(Move::Partner('{', '~')));
+        assert!("pA/b".parse::<Move>().is_err());
+        assert!("pab/c".parse::<Move>().is_err());
+
+        let moves = parse_input("p{/a,s2,x0/27,p}/{").unwrap();
+        let mut dance = Dance::new(30);
+        moves.iter().for_each(|&m| dance.make_move(m));
+
+        assert_eq!(
+            Routine::new(30, &moves).unwrap().lineup(),
+            dance.to_string()
+        );
+    }
+
+    #[test]
+    fn invalid_moves() {
+        let moves = parse_input("s1,x3/4,pe/b").unwrap();
+
+        assert_eq!(
+            Routine::new(4, &moves),
+            Err(DanceError::InvalidMove {
+                index: 1,
+                nb_dancers: 4
+            })
+        );
+        assert_eq!(
+            Routine::new(0, &moves),
+            Err(DanceError::InvalidMove {
+                index: 0,
+                nb_dancers: 0
+            })
+        );
+        assert_eq!(Routine::new(0, &[]).unwrap().lineup(), "");
+        assert!(Routine::new(5, &[Move::Partner('f', 'a')]).is_err());
+        assert!(Day16::parse("s1,pq/a").is_err());
+        assert!(part1(&[Move::Exchange(0, 16)]).is_err());
+        assert!(part2(&[Move::Partner('a', 'q')]).is_err());
+
+        assert_eq!(
+            Routine::new(MAX_DANCERS + 1, &[]),
+            Err(DanceError::TooManyDancers(MAX_DANCERS + 1))
+        );
+        assert!(Routine::new(MAX_DANCERS, &[Move::Partner('\u{d7ff}', 'a')]).is_ok());
+        assert!(Routine::new(MAX_DANCERS, &[Move::Partner('\u{e000}', 'a')]).is_err());
+    }
+
+    #[test]
+    fn parse_error_column() {
+        let err = parse_input("s1,x3/a,pe/b").unwrap_err();
+
+        assert_eq!((err.line, err.column), (1, 7));
+        assert_eq!(err.text, "s1,x3/a,pe/b");
+    }
+
     #[test]
     fn part1_sample() {
         let mut dance = Dance::new(5);
@@ -248,17 +519,57 @@ mod tests {
 
     #[test]
     fn part2_sample() {
-        let dance: Vec<_> = ["s1", "x3/4", "pe/b"]
-            .iter()
-            .filter_map(|m| m.parse::<Move>().ok())
-            .collect();
+        let routine = Routine::new(5, &parse_input("s1,x3/4,pe/b").unwrap()).unwrap();
 
-        let mut fast_dance = FastDance::new(5, &dance);
+        assert_eq!(routine.lineup(), "baedc");
+        assert_eq!(routine.pow(2).lineup(), "ceadb");
+    }
+
+    #[test]
+    fn permutation_algebra() {
+        let p = Permutation::from_indices(vec![1, 2, 0, 4, 3]);
+        let q = Permutation::from_indices(vec![4, 3, 2, 1, 0]);
+
+        assert_eq!(
+            p.then(&q).apply(&[0, 1, 2, 3, 4]),
+            q.apply(&p.apply(&[0, 1, 2, 3, 4]))
+        );
+        assert_eq!(p.then(&p.inverse()), Permutation::identity(5));
+        assert_eq!(p.pow(6), Permutation::identity(5));
+        assert_eq!(p.pow(7), p);
+        assert_eq!(p.cycles(), vec![vec![0, 2, 1], vec![3, 4]]);
+        assert_eq!(
+            p.apply(&['a', 'b', 'c', 'd', 'e']),
+            vec!['b', 'c', 'a', 'e', 'd']
+        );
+    }
+
+    #[test]
+    fn routine_algebra() {
+        let moves = parse_input("s3,x0/7,pc/h,x2/5,pa/b,s11,pe/f").unwrap();
+        let routine = Routine::new(40, &moves).unwrap();
 
-        for _ in 0..2 {
-            fast_dance.dance();
+        let mut dance = Dance::new(40);
+        for _ in 0..3 {
+            moves.iter().for_each(|&m| dance.make_move(m));
         }
 
-        assert_eq!(fast_dance.to_string(), "ceadb");
+        assert_eq!(routine.pow(3).lineup(), dance.to_string());
+        assert_eq!(routine.then(&routine).then(&routine), routine.pow(3));
+        assert_eq!(
+            routine.then(&routine.inverse()).lineup(),
+            Routine::new(40, &[]).unwrap().lineup()
+        );
+        assert_eq!(
+            routine.pow(999_999_999).then(&routine),
+            routine.pow(1_000_000_000)
+        );
+
+        let other = parse_input("pa/z,x1/30,s7").unwrap();
+        let both = moves.iter().chain(&other).cloned().collect::<Vec<_>>();
+        assert_eq!(
+            routine.then(&Routine::new(40, &other).unwrap()),
+            Routine::new(40, &both).unwrap()
+        );
     }
 }
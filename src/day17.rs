@@ -1,31 +1,301 @@
-pub fn part1(input: usize) -> usize {
-    let mut ring_buffer = vec![0];
-    let mut position = 0;
+use solution::Solution;
+
+use std::error::Error;
+
+const NIL: u32 = u32::MAX;
+
+#[derive(Debug, Copy, Clone)]
+struct Node {
+    left: u32,
+    right: u32,
+    parent: u32,
+    size: u32,
+}
+
+/// A sequence of values `0..len()`, each value being its own node, kept as a
+/// treap ordered by position so inserting anywhere takes O(log n).
+#[derive(Debug, Default)]
+struct Sequence {
+    nodes: Vec<Node>,
+    root: u32,
+}
+
+/// The heap priority of a node, a hash of its value so it needs no storage.
+fn priority(node: u32) -> u32 {
+    let mut x = node.wrapping_add(0x9e37_79b9);
+    x = (x ^ (x >> 16)).wrapping_mul(0x85eb_ca6b);
+    x = (x ^ (x >> 13)).wrapping_mul(0xc2b2_ae35);
+    x ^ (x >> 16)
+}
+
+impl Sequence {
+    fn size(&self, node: u32) -> usize {
+        if node == NIL {
+            0
+        } else {
+            self.nodes[node as usize].size as usize
+        }
+    }
+
+    /// Moves `node` above its parent, keeping the order of the values.
+    fn rotate_up(&mut self, node: u32) {
+        let parent = self.nodes[node as usize].parent;
+        let grandparent = self.nodes[parent as usize].parent;
+
+        if self.nodes[parent as usize].left == node {
+            let moved = self.nodes[node as usize].right;
+            self.nodes[parent as usize].left = moved;
+            self.nodes[node as usize].right = parent;
+
+            if moved != NIL {
+                self.nodes[moved as usize].parent = parent;
+            }
+        } else {
+            let moved = self.nodes[node as usize].left;
+            self.nodes[parent as usize].right = moved;
+            self.nodes[node as usize].left = parent;
+
+            if moved != NIL {
+                self.nodes[moved as usize].parent = parent;
+            }
+        }
+
+        self.nodes[parent as usize].parent = node;
+        self.nodes[node as usize].parent = grandparent;
+
+        if grandparent == NIL {
+            self.root = node;
+        } else if self.nodes[grandparent as usize].left == parent {
+            self.nodes[grandparent as usize].left = node;
+        } else {
+            self.nodes[grandparent as usize].right = node;
+        }
+
+        // The subtree keeps its values, now under `node`.
+        self.nodes[node as usize].size = self.nodes[parent as usize].size;
+
+        let Node { left, right, .. } = self.nodes[parent as usize];
+        self.nodes[parent as usize].size = (1 + self.size(left) + self.size(right)) as u32;
+    }
+
+    fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Inserts the next value, `len()`, at `index`: down to a leaf, then up
+    /// while its priority is higher than its parent's.
+    fn insert(&mut self, mut index: usize) {
+        let value = self.nodes.len() as u32;
+        assert!(value != NIL, "spinlock buffer full");
+
+        self.nodes.push(Node {
+            left: NIL,
+            right: NIL,
+            parent: NIL,
+            size: 1,
+        });
+
+        if value == 0 {
+            self.root = value;
+            return;
+        }
+
+        let mut node = self.root;
+
+        loop {
+            self.nodes[node as usize].size += 1;
+
+            let Node { left, right, .. } = self.nodes[node as usize];
+            let left_size = self.size(left);
+
+            let child = if index <= left_size {
+                if left == NIL {
+                    self.nodes[node as usize].left = value;
+                    break;
+                }
+                left
+            } else {
+                index -= left_size + 1;
+
+                if right == NIL {
+                    self.nodes[node as usize].right = value;
+                    break;
+                }
+                right
+            };
+
+            node = child;
+        }
+
+        self.nodes[value as usize].parent = node;
+
+        while self.nodes[value as usize].parent != NIL
+            && priority(value) > priority(self.nodes[value as usize].parent)
+        {
+            self.rotate_up(value);
+        }
+    }
+
+    fn get(&self, mut index: usize) -> Option<usize> {
+        let mut node = self.root;
+
+        while node != NIL {
+            let Node { left, right, .. } = self.nodes[node as usize];
+            let left_size = self.size(left);
+
+            if index < left_size {
+                node = left;
+            } else if index == left_size {
+                return Some(node as usize);
+            } else {
+                index -= left_size + 1;
+                node = right;
+            }
+        }
+
+        None
+    }
+
+    /// The position of `value`, walking up to the root.
+    fn position(&self, value: usize) -> Option<usize> {
+        if value >= self.len() {
+            return None;
+        }
+
+        let mut node = value as u32;
+        let mut position = self.size(self.nodes[value].left);
+
+        loop {
+            let parent = self.nodes[node as usize].parent;
+
+            if parent == NIL {
+                return Some(position);
+            }
+
+            if self.nodes[parent as usize].right == node {
+                position += self.size(self.nodes[parent as usize].left) + 1;
+            }
+
+            node = parent;
+        }
+    }
+}
+
+/// The circular buffer of the spinlock, starting with a single 0.
+#[derive(Debug)]
+pub struct Spinlock {
+    steps: usize,
+    buffer: Sequence,
+    position: usize,
+}
+
+impl Spinlock {
+    pub fn new(steps: usize) -> Spinlock {
+        let mut buffer = Sequence::default();
+        buffer.insert(0);
+
+        Spinlock {
+            steps,
+            buffer,
+            position: 0,
+        }
+    }
 
-    for i in 1..2018 {
-        position = (position + input) % ring_buffer.len() + 1;
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
 
-        ring_buffer.insert(position, i);
+    /// Never: 0 is always in the buffer.
+    pub fn is_empty(&self) -> bool {
+        false
     }
 
-    ring_buffer[(position + 1) % ring_buffer.len()]
+    /// Steps forward and inserts the next value after the current position.
+    pub fn insert(&mut self) {
+        self.position = (self.position + self.steps) % self.len() + 1;
+        self.buffer.insert(self.position);
+    }
+
+    pub fn run(&mut self, insertions: usize) {
+        for _ in 0..insertions {
+            self.insert();
+        }
+    }
+
+    /// The value at the current position, the last one inserted.
+    pub fn current(&self) -> usize {
+        self.len() - 1
+    }
+
+    /// The value at `index` from the start of the buffer.
+    pub fn get(&self, index: usize) -> Option<usize> {
+        self.buffer.get(index)
+    }
+
+    pub fn position_of(&self, value: usize) -> Option<usize> {
+        self.buffer.position(value)
+    }
+
+    /// The value following `value` in the circular buffer.
+    pub fn value_after(&self, value: usize) -> Option<usize> {
+        let position = self.position_of(value)?;
+
+        self.get((position + 1) % self.len())
+    }
 }
 
-pub fn part2(input: i32) -> i32 {
+/// The value after 0 once `insertions` values are inserted, without building
+/// the buffer: 0 stays first, so only insertions at position 1 matter.
+pub fn value_after_zero(steps: usize, insertions: usize) -> usize {
     let mut position = 0;
     let mut result = 0;
 
-    for buffer_length in 1..50_000_001 {
-        position = (position + input) % buffer_length + 1;
+    for len in 1..=insertions {
+        position = (position + steps) % len + 1;
 
         if position == 1 {
-            result = buffer_length;
+            result = len;
         }
     }
 
     result
 }
 
+pub fn part1(input: usize) -> usize {
+    let mut spinlock = Spinlock::new(input);
+    spinlock.run(2017);
+
+    spinlock
+        .value_after(spinlock.current())
+        .expect("the buffer holds the current value")
+}
+
+pub fn part2(input: usize) -> usize {
+    value_after_zero(input, 50_000_000)
+}
+
+pub struct Day17;
+
+impl Solution for Day17 {
+    const DAY: u32 = 17;
+
+    type Input = usize;
+    type Output1 = usize;
+    type Output2 = usize;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(input.trim().parse()?)
+    }
+
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(*input)
+    }
+
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(*input)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -34,4 +304,45 @@ mod tests {
     fn part1_sample() {
         assert_eq!(part1(3), 638);
     }
+
+    #[test]
+    fn sample_buffer() {
+        let mut spinlock = Spinlock::new(3);
+        spinlock.run(9);
+
+        // 0 (9) 5  7  2  4  3  8  6  1
+        let buffer = (0..10).map(|i| spinlock.get(i).unwrap()).collect::<Vec<_>>();
+        assert_eq!(buffer, vec![0, 9, 5, 7, 2, 4, 3, 8, 6, 1]);
+
+        assert_eq!(spinlock.current(), 9);
+        assert_eq!(spinlock.position_of(2), Some(4));
+        assert_eq!(spinlock.value_after(1), Some(0));
+        assert_eq!(spinlock.get(10), None);
+        assert_eq!(spinlock.position_of(10), None);
+    }
+
+    #[test]
+    fn matches_vec_simulation() {
+        let mut buffer = vec![0];
+        let mut position = 0;
+        let mut spinlock = Spinlock::new(348);
+
+        for value in 1..5_000 {
+            position = (position + 348) % buffer.len() + 1;
+            buffer.insert(position, value);
+            spinlock.insert();
+        }
+
+        assert!(buffer.iter().enumerate().all(|(i, &v)| spinlock.get(i) == Some(v)));
+        assert!(buffer.iter().enumerate().all(|(i, &v)| spinlock.position_of(v) == Some(i)));
+    }
+
+    #[test]
+    fn many_insertions() {
+        let mut spinlock = Spinlock::new(348);
+        spinlock.run(200_000);
+
+        assert_eq!(spinlock.value_after(0), Some(value_after_zero(348, 200_000)));
+        assert_eq!(spinlock.get(0), Some(0));
+    }
 }
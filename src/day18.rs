@@ -1,173 +1,84 @@
-use day18_parser::{parse, Instruction, Value};
+use day18_parser::{parse, Instruction};
+use solution::Solution;
+use vm::scheduler::{Network, Topology, WiringError};
+use vm::{Halt, Machine, Sound};
 
-use std::collections::HashMap;
+use std::error::Error;
 
-struct Duet {
-    registers: HashMap<char, i64>,
-    sound_frequency: Option<i64>,
-    instructions: Vec<Instruction>,
-    program_counter: usize,
-}
-
-impl Duet {
-    fn new(instructions: Vec<Instruction>) -> Duet {
-        Duet {
-            registers: HashMap::new(),
-            sound_frequency: None,
-            instructions,
-            program_counter: 0,
-        }
-    }
-
-    fn run(&mut self, instruction: Instruction) {
-        let mut advance_pc = true;
-        match instruction {
-            Instruction::Snd(val) => self.sound_frequency = Some(self.get_val(val)),
-            Instruction::Set(reg, val) => {
-                let val = self.get_val(val);
-                self.set(reg, val);
-            }
-            Instruction::Add(reg, val) => {
-                let val = self.get(reg) + self.get_val(val);
-                self.set(reg, val);
-            }
-            Instruction::Mul(reg, val) => {
-                let val = self.get(reg) * self.get_val(val);
-                self.set(reg, val);
-            }
-            Instruction::Mod(reg, val) => {
-                let val = self.get(reg) % self.get_val(val);
-                self.set(reg, val);
-            }
-            Instruction::Rcv(reg) => {
-                if let Some(val) = self.sound_frequency {
-                    self.set(reg, val);
-                }
-            }
-            Instruction::Jgz(val, offset) => {
-                if self.get_val(val) > 0 {
-                    self.program_counter =
-                        (self.program_counter as i64 + self.get_val(offset)) as usize;
-                    advance_pc = false;
-                }
-            }
-        }
-
-        if advance_pc {
-            self.program_counter += 1;
-        }
-    }
-
-    fn get(&self, reg: char) -> i64 {
-        *self.registers.get(&reg).unwrap_or(&0)
-    }
-
-    fn set(&mut self, reg: char, value: i64) {
-        self.registers.insert(reg, value);
-    }
-
-    fn get_val(&self, value: Value) -> i64 {
-        match value {
-            Value::Register(reg) => self.get(reg),
-            Value::Literal(val) => val,
-        }
-    }
+pub fn part1(input: &[Instruction]) -> i64 {
+    let mut duet: Machine<_> = Machine::new(input.to_vec());
+    let mut sound = Sound::default();
 
-    fn run_until_recover(&mut self) -> i64 {
-        loop {
-            let instruction = self.instructions[self.program_counter];
+    assert_eq!(duet.run(&mut sound), Halt::Receive);
 
-            self.run(instruction);
+    sound.recovered.expect("no sound recovered")
+}
 
-            if let (Instruction::Rcv(_), Some(val)) = (instruction, self.sound_frequency) {
-                return val;
-            }
-        }
-    }
+/// `n` copies of the program, each one with its id in register `p`.
+pub fn network(
+    input: &[Instruction],
+    n: usize,
+    topology: &Topology,
+) -> Result<Network<Instruction>, WiringError> {
+    let machines = (0..n)
+        .map(|p| {
+            let mut duet = Machine::new(input.to_vec());
+            duet.set('p', p as i64);
+            duet
+        })
+        .collect();
+
+    Network::new(machines, topology)
 }
 
-pub fn part1(input: &str) -> i64 {
-    let mut duet = Duet::new(parse(input));
+pub fn part2(input: &[Instruction]) -> usize {
+    let mut network = network(input, 2, &Topology::Ring).expect("a ring fits any programs");
 
-    duet.run_until_recover()
-}
+    network.run();
 
-struct SyncDuet {
-    duet: Duet,
-    channel_counter: usize,
+    network.stats()[1].sent
 }
 
-impl SyncDuet {
-    fn new(instructions: Vec<Instruction>, p: i64) -> SyncDuet {
-        let mut duet = Duet::new(instructions);
+/// Same as `part2`, the two programs running on their own threads.
+pub fn part2_threaded(input: &[Instruction]) -> usize {
+    let mut network = network(input, 2, &Topology::Ring).expect("a ring fits any programs");
 
-        duet.set('p', p);
-
-        SyncDuet {
-            duet,
-            channel_counter: 0,
-        }
-    }
+    network.run_threaded();
 
-    fn run_until_block(&mut self, other_channel: &[i64]) -> Vec<i64> {
-        let mut channel = Vec::new();
-
-        while let Some(&instruction) = self.duet.instructions.get(self.duet.program_counter) {
-            match instruction {
-                Instruction::Snd(val) => {
-                    channel.push(self.duet.get_val(val));
-                    self.duet.program_counter += 1;
-                }
-                Instruction::Rcv(reg) => {
-                    if let Some(&val) = other_channel.get(self.channel_counter) {
-                        self.duet.set(reg, val);
-                        self.channel_counter += 1;
-                        self.duet.program_counter += 1;
-                    } else {
-                        break;
-                    }
-                }
-                i => self.duet.run(i),
-            }
-        }
-
-        channel
-    }
+    network.stats()[1].sent
 }
 
-pub fn part2(input: &str) -> usize {
-    let instruction = parse(input);
-    let mut duet0 = SyncDuet::new(instruction.clone(), 0);
-    let mut duet1 = SyncDuet::new(instruction, 1);
-
-    let mut duet0_channel = Vec::new();
-    let mut duet1_channel = Vec::new();
+pub struct Day18;
 
-    loop {
-        let mut duet0_generated = duet0.run_until_block(&duet1_channel);
-        let duet0_nb_generated = duet0_generated.len();
+impl Solution for Day18 {
+    const DAY: u32 = 18;
 
-        duet0_channel.append(&mut duet0_generated);
+    type Input = Vec<Instruction>;
+    type Output1 = i64;
+    type Output2 = usize;
 
-        let mut duet1_generated = duet1.run_until_block(&duet0_channel);
-        let duet1_nb_generated = duet1_generated.len();
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(parse(input.trim())?)
+    }
 
-        duet1_channel.append(&mut duet1_generated);
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input)
+    }
 
-        if duet0_nb_generated == 0 && duet1_nb_generated == 0 {
-            return duet1_channel.len();
-        }
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use input;
 
     #[test]
     fn part1_sample() {
         assert_eq!(
-            part1(
+            part1(&parse(
                 "set a 1
 add a 2
 mul a a
@@ -178,7 +89,7 @@ rcv a
 jgz a -1
 set a 1
 jgz a -2",
-            ),
+            ).unwrap()),
             4
         )
     }
@@ -186,7 +97,7 @@ jgz a -2",
     #[test]
     fn part2_sample() {
         assert_eq!(
-            part2(
+            part2(&parse(
                 "snd 1
 snd 2
 snd p
@@ -194,8 +105,36 @@ rcv a
 rcv b
 rcv c
 rcv d",
-            ),
+            ).unwrap()),
             3
         )
     }
+
+    #[test]
+    fn larger_networks() {
+        let input = parse("snd 1\nsnd 2\nsnd p\nrcv a\nrcv b\nrcv c\nrcv d").unwrap();
+
+        let mut ring = network(&input, 4, &Topology::Ring).unwrap();
+        ring.run();
+
+        assert!(ring.stats().iter().all(|s| s.sent == 3 && s.received == 3));
+        assert_eq!(ring.machines()[0].get('c'), 3);
+
+        let mut mesh = network(&input, 4, &Topology::AllToAll).unwrap();
+        mesh.run();
+
+        assert!(mesh.stats().iter().all(|s| s.sent == 3 && s.received == 4));
+    }
+
+    #[test]
+    fn threaded_matches_cooperative() {
+        let input = parse(input::embedded(18).unwrap().trim()).unwrap();
+
+        let mut cooperative = network(&input, 2, &Topology::Ring).unwrap();
+        let mut threaded = network(&input, 2, &Topology::Ring).unwrap();
+
+        assert_eq!(cooperative.run(), threaded.run_threaded());
+        assert_eq!(cooperative.stats(), threaded.stats());
+        assert_eq!(part2_threaded(&input), 7112);
+    }
 }
@@ -1,3 +1,7 @@
+use solution::Solution;
+
+use std::error::Error;
+
 type Tubes = Vec<Vec<Cell>>;
 
 #[derive(Debug)]
@@ -149,7 +153,7 @@ impl Cell {
             '|' => Cell::Vertical,
             '-' => Cell::Horizontal,
             '+' => Cell::Angle,
-            'A'...'Z' => Cell::Letter(c),
+            'A'..='Z' => Cell::Letter(c),
             _ => unreachable!("wrong char"),
         }
     }
@@ -183,6 +187,29 @@ pub fn part2(input: &str) -> u32 {
     unreachable!()
 }
 
+pub struct Day19;
+
+impl Solution for Day19 {
+    const DAY: u32 = 19;
+
+    type Input = String;
+    type Output1 = String;
+    type Output2 = u32;
+
+    /// The routing diagram is whitespace sensitive, so it isn't trimmed.
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(input.to_owned())
+    }
+
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input)
+    }
+
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,3 +1,7 @@
+use solution::Solution;
+
+use std::error::Error;
+
 fn line_min_max(line: &str) -> Option<(u32, u32)> {
     line.split_whitespace()
         .filter_map(|number| number.parse::<u32>().ok())
@@ -44,6 +48,28 @@ pub fn part2(input: &str) -> u32 {
         .sum()
 }
 
+pub struct Day2;
+
+impl Solution for Day2 {
+    const DAY: u32 = 2;
+
+    type Input = String;
+    type Output1 = u32;
+    type Output2 = u32;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(input.trim().to_owned())
+    }
+
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input)
+    }
+
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
+    }
+}
+
 #[cfg(test)]
 mod tests_part1 {
     //! For example, given the following spreadsheet:
@@ -1,9 +1,13 @@
-use day20_parser::{parse, Int, Vec3};
+use day20_parser::{parse, Int, ParseError, Vec3};
+use num_bigint::{BigInt, Sign};
+use solution::Solution;
 
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error::Error;
 
-#[derive(Debug, Eq, PartialEq)]
-struct Particle {
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Particle {
     position: Vec3,
     velocity: Vec3,
     acceleration: Vec3,
@@ -18,22 +22,251 @@ impl Particle {
         }
     }
 
-    fn pos_at(&self, time: Int) -> Vec3 {
-        let a = self.acceleration;
-        let v = self.velocity;
-        let p = self.position;
+    /// On each axis, the coefficients of twice the position after `t` ticks,
+    /// `a t² + b t + c`, so that they stay integers.
+    fn coefficients(&self) -> [(i128, i128, i128); 3] {
+        let (a, v, p) = (
+            self.acceleration.axes(),
+            self.velocity.axes(),
+            self.position.axes(),
+        );
+        let axis = |i: usize| {
+            let (a, v, p) = (i128::from(a[i]), i128::from(v[i]), i128::from(p[i]));
+
+            (a, a + 2 * v, 2 * p)
+        };
+
+        [0, 1, 2].map(axis)
+    }
+
+    /// The position after `time` ticks, `None` if it is out of the range of
+    /// `Int`.
+    pub fn position_at(&self, time: i128) -> Option<Vec3> {
+        let mut axes = [0; 3];
+
+        for (axis, &(a, b, c)) in axes.iter_mut().zip(&self.coefficients()) {
+            *axis = Int::try_from(eval(a, b, c, time)? / 2).ok()?;
+        }
 
-        a * (time * time) + (a + v * 2) * time + p * 2
+        Some(Vec3::new(axes[0], axes[1], axes[2]))
     }
+
+    /// Every tick at which both particles are at the same position, if they
+    /// were left alone.
+    pub fn collision_times(&self, other: &Particle) -> Vec<i128> {
+        // The coefficients of twice the difference of the particles.
+        let (first, second) = (self.coefficients(), other.coefficients());
+        let difference = [0, 1, 2].map(|i| {
+            let ((a, b, c), (d, e, f)) = (first[i], second[i]);
+
+            (a - d, b - e, c - f)
+        });
+
+        match difference.iter().find(|&&axis| axis != (0, 0, 0)) {
+            // The roots on one axis, the others must agree.
+            Some(&(a, b, c)) => integer_roots(a, b, c)
+                .into_iter()
+                .filter(|&time| {
+                    difference
+                        .iter()
+                        .all(|&(a, b, c)| eval(a, b, c, time) == Some(0))
+                })
+                .collect(),
+            // Always together: they collide at once.
+            None => vec![0],
+        }
+    }
+}
+
+/// `a t² + b t + c` for `t >= 0`, `None` if it overflows.
+///
+/// With coefficients from `Int` values, an overflow means the result is far
+/// from 0, and from `2 * Int::MAX`.
+fn eval(a: i128, b: i128, c: i128, t: i128) -> Option<i128> {
+    a.checked_mul(t)?
+        .checked_add(b)?
+        .checked_mul(t)?
+        .checked_add(c)
 }
 
-pub fn part1(input: &str) -> usize {
-    let mut particles = parse(input, Particle::new)
+/// The non-negative integer roots of `a t² + b t + c`, not all 0, in
+/// increasing order.
+fn integer_roots(a: i128, b: i128, c: i128) -> Vec<i128> {
+    let candidates = if a == 0 {
+        if b != 0 && c % b == 0 {
+            vec![-c / b]
+        } else {
+            vec![]
+        }
+    } else {
+        let root = match discriminant_root(a, b, c) {
+            Some(root) => root,
+            None => return vec![],
+        };
+
+        [-b - root, -b + root]
+            .iter()
+            .filter(|&&n| n % (2 * a) == 0)
+            .map(|&n| n / (2 * a))
+            .collect()
+    };
+
+    let mut roots = candidates
         .into_iter()
+        .filter(|&t| t >= 0)
+        .collect::<Vec<_>>();
+
+    roots.sort_unstable();
+    roots.dedup();
+    roots
+}
+
+/// The square root of `b² - 4 a c`, if it is a perfect square.
+///
+/// The discriminant of coefficients from `Int` values may not fit in `i128`,
+/// but its root does.
+fn discriminant_root(a: i128, b: i128, c: i128) -> Option<i128> {
+    let discriminant = b
+        .checked_mul(b)
+        .and_then(|b2| b2.checked_sub(a.checked_mul(4)?.checked_mul(c)?));
+
+    match discriminant {
+        Some(discriminant) if discriminant >= 0 => {
+            let root = isqrt(discriminant);
+
+            if root * root == discriminant {
+                Some(root)
+            } else {
+                None
+            }
+        }
+        Some(_) => None,
+        None => {
+            let (a, b, c) = (BigInt::from(a), BigInt::from(b), BigInt::from(c));
+            let discriminant = &b * &b - BigInt::from(4) * a * c;
+
+            if discriminant.sign() == Sign::Minus {
+                return None;
+            }
+
+            let root = discriminant.sqrt();
+
+            if &root * &root == discriminant {
+                i128::try_from(root).ok()
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// The largest `r` such that `r * r <= n`.
+fn isqrt(n: i128) -> i128 {
+    let mut r = (n as f64).sqrt() as i128;
+
+    while r * r > n {
+        r -= 1;
+    }
+
+    while (r + 1) * (r + 1) <= n {
+        r += 1;
+    }
+
+    r
+}
+
+/// Particles destroyed together.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Collision {
+    pub time: i128,
+    /// `None` if it is out of the range of `Int`.
+    pub position: Option<Vec3>,
+    /// Indices in the input, in increasing order.
+    pub particles: Vec<usize>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Outcome {
+    /// Indices of the particles never destroyed, in increasing order.
+    pub survivors: Vec<usize>,
+    /// In time order, then by smallest particle.
+    pub collisions: Vec<Collision>,
+}
+
+/// Finds every collision exactly, from the times each pair would meet.
+///
+/// Pairs are only checked at the ticks they would meet, so this terminates
+/// with every collision that can ever happen, however late.
+pub fn collide(particles: &[Particle]) -> Outcome {
+    let mut events = Vec::new();
+
+    for (i, first) in particles.iter().enumerate() {
+        for (j, second) in particles.iter().enumerate().skip(i + 1) {
+            events.extend(
+                first
+                    .collision_times(second)
+                    .into_iter()
+                    .map(|time| (time, i, j)),
+            );
+        }
+    }
+
+    events.sort_unstable();
+
+    let mut alive = vec![true; particles.len()];
+    let mut collisions = Vec::new();
+
+    for group in events.chunk_by(|first, second| first.0 == second.0) {
+        let time = group[0].0;
+        let mut crowds: Vec<Vec<usize>> = Vec::new();
+        let mut crowd_of = HashMap::new();
+
+        // Only the particles still there at the start of the tick count.
+        // Meeting is transitive, and pairs come by their smallest particle,
+        // so each crowd is first seen with its smallest particle.
+        for &(_, i, j) in group.iter().filter(|&&(_, i, j)| alive[i] && alive[j]) {
+            let crowd = *crowd_of.entry(i).or_insert_with(|| {
+                crowds.push(vec![i]);
+                crowds.len() - 1
+            });
+
+            if crowd_of.insert(j, crowd).is_none() {
+                crowds[crowd].push(j);
+            }
+        }
+
+        for mut particles_hit in crowds {
+            particles_hit.sort_unstable();
+
+            for &i in &particles_hit {
+                alive[i] = false;
+            }
+
+            collisions.push(Collision {
+                time,
+                position: particles[particles_hit[0]].position_at(time),
+                particles: particles_hit,
+            });
+        }
+    }
+
+    Outcome {
+        survivors: (0..particles.len()).filter(|&i| alive[i]).collect(),
+        collisions,
+    }
+}
+
+fn parse_input(input: &str) -> Result<Vec<Particle>, ParseError> {
+    parse(input, Particle::new)
+}
+
+pub fn part1(input: &[Particle]) -> usize {
+    let mut particles = input
+        .iter()
         .enumerate()
         .collect::<Vec<_>>();
 
-    particles.sort_by(|&(_, ref a), &(_, ref b)| {
+    particles.sort_by(|(_, a), (_, b)| {
         a.acceleration
             .cmp(&b.acceleration)
             .then(a.velocity.cmp(&b.velocity))
@@ -42,44 +275,30 @@ pub fn part1(input: &str) -> usize {
     particles[0].0
 }
 
-pub fn part2(input: &str) -> usize {
-    let mut particles = parse(input, Particle::new);
+pub fn part2(input: &[Particle]) -> usize {
+    collide(input).survivors.len()
+}
+
+pub struct Day20;
 
-    for t in 0..1000 {
-        let mut collisions: HashMap<Vec3, Vec<usize>> = HashMap::new();
+impl Solution for Day20 {
+    const DAY: u32 = 20;
 
-        for (i, pos) in particles
-            .iter()
-            .enumerate()
-            .rev()
-            .map(|(i, p)| (i, p.pos_at(t)))
-        {
-            let par_at_pos = collisions.entry(pos).or_insert_with(Vec::new);
-            par_at_pos.push(i);
-        }
+    type Input = Vec<Particle>;
+    type Output1 = usize;
+    type Output2 = usize;
 
-        let to_ignore = collisions.values_mut().filter(|v| v.len() > 1).fold(
-            Vec::new(),
-            |mut total, to_ignore| {
-                total.append(to_ignore);
-                total
-            },
-        );
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(parse_input(input.trim())?)
+    }
 
-        particles = particles
-            .into_iter()
-            .enumerate()
-            .filter_map(|(i, p)| {
-                if to_ignore.contains(&i) {
-                    None
-                } else {
-                    Some(p)
-                }
-            })
-            .collect();
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input)
     }
 
-    particles.len()
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
+    }
 }
 
 #[cfg(test)]
@@ -90,6 +309,123 @@ mod tests {
 
     #[test]
     fn part1_sample() {
-        assert_eq!(part1(SAMPLE_PART1), 0);
+        assert_eq!(part1(&parse_input(SAMPLE_PART1).unwrap()), 0);
+    }
+
+    const SAMPLE_PART2: &str = "p=<-6,0,0>, v=<3,0,0>, a=<0,0,0>
+p=<-4,0,0>, v=<2,0,0>, a=<0,0,0>
+p=<-2,0,0>, v=<1,0,0>, a=<0,0,0>
+p=<3,0,0>, v=<-1,0,0>, a=<0,0,0>";
+
+    #[test]
+    fn part2_sample() {
+        let particles = parse_input(SAMPLE_PART2).unwrap();
+
+        assert_eq!(
+            collide(&particles),
+            Outcome {
+                survivors: vec![3],
+                collisions: vec![Collision {
+                    time: 2,
+                    position: Some(Vec3::new(0, 0, 0)),
+                    particles: vec![0, 1, 2],
+                }],
+            }
+        );
+        assert_eq!(part2(&particles), 1);
+    }
+
+    #[test]
+    fn roots() {
+        // (t - 2)(t - 5)
+        assert_eq!(integer_roots(1, -7, 10), vec![2, 5]);
+        // (2t - 3)(t - 4): 1.5 is not a tick.
+        assert_eq!(integer_roots(2, -11, 12), vec![4]);
+        assert_eq!(integer_roots(1, 2, 1), vec![]);
+        assert_eq!(integer_roots(0, 3, -12), vec![4]);
+        assert_eq!(integer_roots(0, 0, 5), vec![]);
+    }
+
+    #[test]
+    fn late_collisions() {
+        let particles = parse_input(
+            "p=<0,0,0>, v=<0,0,0>, a=<0,0,0>
+p=<-5000,0,0>, v=<1,0,0>, a=<0,0,0>
+p=<-5000,0,0>, v=<1,0,0>, a=<0,0,0>
+p=<1,1,1>, v=<0,0,0>, a=<0,0,0>",
+        )
+        .unwrap();
+
+        let outcome = collide(&particles);
+
+        // 1 and 2 are together from the start, so 0 is never hit.
+        assert_eq!(outcome.survivors, vec![0, 3]);
+        assert_eq!(outcome.collisions.len(), 1);
+        assert_eq!(outcome.collisions[0].time, 0);
+
+        let particles = parse_input(
+            "p=<0,0,0>, v=<0,0,0>, a=<0,0,0>
+p=<-5000,0,0>, v=<1,0,0>, a=<0,0,0>",
+        )
+        .unwrap();
+
+        assert_eq!(collide(&particles).collisions[0].time, 5000);
+    }
+
+    #[test]
+    fn accelerating() {
+        let first = Particle::new((Vec3::new(0, 0, 0), Vec3::new(0, 0, 0), Vec3::new(1, 0, 0)));
+        let second = Particle::new((Vec3::new(6, 0, 0), Vec3::new(0, 0, 0), Vec3::new(0, 0, 0)));
+
+        // The first one is at 1, 3, 6, 10... after each tick.
+        assert_eq!(first.position_at(3), Some(Vec3::new(6, 0, 0)));
+        assert_eq!(first.collision_times(&second), vec![3]);
+    }
+
+    #[test]
+    fn far_collisions() {
+        // Twice the position along y overflows an `Int` on the way.
+        let particles = parse_input(
+            "p=<-1000000000,0,0>, v=<1,0,0>, a=<0,4,0>
+p=<0,0,0>, v=<0,0,0>, a=<0,4,0>
+p=<-2000000000,0,1>, v=<2,0,0>, a=<0,20,0>
+p=<0,0,1>, v=<0,0,0>, a=<0,20,0>",
+        )
+        .unwrap();
+
+        let collisions = collide(&particles).collisions;
+        assert_eq!(collisions.len(), 2);
+        assert_eq!(collisions[0].time, 1_000_000_000);
+        assert_eq!(
+            collisions[0].position,
+            Some(Vec3::new(0, 2_000_000_002_000_000_000, 0))
+        );
+        // 10^19 is beyond `Int`.
+        assert_eq!(collisions[1].particles, vec![2, 3]);
+        assert_eq!(collisions[1].position, None);
+
+        // Later than `Int::MAX` ticks.
+        let particles = parse_input(
+            "p=<-9000000000000000000,0,0>, v=<0,0,0>, a=<0,0,0>
+p=<9000000000000000000,0,0>, v=<-1,0,0>, a=<0,0,0>",
+        )
+        .unwrap();
+
+        assert_eq!(
+            collide(&particles).collisions,
+            vec![Collision {
+                time: 18_000_000_000_000_000_000,
+                position: Some(Vec3::new(-9_000_000_000_000_000_000, 0, 0)),
+                particles: vec![0, 1],
+            }]
+        );
+    }
+
+    #[test]
+    fn huge_discriminant() {
+        // (2^62 t - 1)(t - 2^62) has a discriminant beyond i128.
+        let a = 1 << 62;
+
+        assert_eq!(integer_roots(a, -(a * a) - 1, a), vec![a]);
     }
 }
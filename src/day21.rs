@@ -1,72 +1,279 @@
-use day21_parser::parse;
+use day21_parser::{parse, ParseError};
+use num_bigint::BigUint;
+use parse_error::ErrorKind;
+use render::{Frames, Image};
+use solution::Solution;
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
 
 type PixelGrid = Vec<Vec<bool>>;
 
-fn mirror_vertical<T: Copy>(grid: &[Vec<T>]) -> Vec<Vec<T>> {
-    grid.iter()
-        .cloned()
-        .map(|row| row.into_iter().rev().collect())
-        .collect()
+/// A square of up to 4×4 pixels, pixel `(row, col)` being bit
+/// `row * size + col`.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Copy, Clone)]
+pub struct Pattern {
+    size: u8,
+    bits: u16,
 }
 
-fn mirror_horizontal<T: Copy>(grid: &[Vec<T>]) -> Vec<Vec<T>> {
-    grid.iter().cloned().rev().collect()
-}
+impl Pattern {
+    /// The pattern of `rows`, if they make a square of 1 to 4 pixels a side.
+    pub fn from_rows(rows: &[Vec<bool>]) -> Option<Pattern> {
+        let size = rows.len();
+
+        if size == 0 || size > 4 || rows.iter().any(|row| row.len() != size) {
+            return None;
+        }
+
+        let bits = rows
+            .iter()
+            .flatten()
+            .enumerate()
+            .filter(|&(_, &on)| on)
+            .fold(0, |bits, (i, _)| bits | 1 << i);
+
+        Some(Pattern {
+            size: size as u8,
+            bits,
+        })
+    }
+
+    /// Every pattern of a size, from all pixels off.
+    pub fn all(size: usize) -> impl Iterator<Item = Pattern> {
+        assert!(size > 0 && size <= 4, "patterns are 1 to 4 pixels wide");
+
+        (0..1u32 << (size * size)).map(move |bits| Pattern {
+            size: size as u8,
+            bits: bits as u16,
+        })
+    }
+
+    pub fn size(self) -> usize {
+        usize::from(self.size)
+    }
 
-fn mirror_diagonal<T: Copy>(grid: &[Vec<T>]) -> Vec<Vec<T>> {
-    if grid.is_empty() {
-        return Vec::new();
+    pub fn get(self, row: usize, col: usize) -> bool {
+        self.bits & 1 << (row * self.size() + col) != 0
     }
 
-    let row_len = grid[0].len();
+    pub fn count_on(self) -> usize {
+        self.bits.count_ones() as usize
+    }
+
+    pub fn rows(self) -> PixelGrid {
+        (0..self.size())
+            .map(|row| (0..self.size()).map(|col| self.get(row, col)).collect())
+            .collect()
+    }
+
+    /// The pattern whose pixel `(row, col)` is `source(row, col)` in this one.
+    fn remap<F: Fn(usize, usize) -> (usize, usize)>(self, source: F) -> Pattern {
+        let n = self.size();
+        let mut bits = 0;
+
+        for row in 0..n {
+            for col in 0..n {
+                let (r, c) = source(row, col);
+
+                if self.get(r, c) {
+                    bits |= 1 << (row * n + col);
+                }
+            }
+        }
+
+        Pattern { bits, ..self }
+    }
+
+    /// Left to right.
+    pub fn mirror_vertical(self) -> Pattern {
+        let last = self.size() - 1;
+        self.remap(|row, col| (row, last - col))
+    }
+
+    /// Top to bottom.
+    pub fn mirror_horizontal(self) -> Pattern {
+        let last = self.size() - 1;
+        self.remap(|row, col| (last - row, col))
+    }
+
+    /// Rows to columns.
+    pub fn mirror_diagonal(self) -> Pattern {
+        self.remap(|row, col| (col, row))
+    }
+
+    /// A quarter turn clockwise.
+    pub fn rotate(self) -> Pattern {
+        self.mirror_diagonal().mirror_vertical()
+    }
+
+    /// The 8 rotations and reflections of the pattern, some of them equal for
+    /// symmetric patterns.
+    pub fn symmetries(self) -> [Pattern; 8] {
+        let mut symmetries = [self; 8];
+
+        for i in 1..8 {
+            symmetries[i] = if i == 4 {
+                self.mirror_diagonal()
+            } else {
+                symmetries[i - 1].rotate()
+            };
+        }
+
+        symmetries
+    }
+
+    /// The same pattern for all the rotations and reflections of this one.
+    pub fn canonical(self) -> Pattern {
+        *self
+            .symmetries()
+            .iter()
+            .min()
+            .expect("a pattern has symmetries")
+    }
+}
+
+/// As in the rules: `#` for on, `.` for off, rows separated with `/`.
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in 0..self.size() {
+            if row > 0 {
+                f.write_str("/")?;
+            }
+
+            for col in 0..self.size() {
+                f.write_str(if self.get(row, col) { "#" } else { "." })?;
+            }
+        }
 
-    let mut result = Vec::with_capacity(row_len);
+        Ok(())
+    }
+}
 
-    for y in 0..row_len {
-        result.push(Vec::with_capacity(grid.len()));
+/// Something odd about a rulebook, rules being numbered from 1 in input order.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Issue {
+    /// A rule matching a rotation or reflection of an earlier rule, with
+    /// another output. The earlier rule is used.
+    Conflict { first: usize, second: usize },
+    /// A rule matching a rotation or reflection of an earlier rule, with the
+    /// same output.
+    Duplicate { first: usize, second: usize },
+    /// A 2×2 or 3×3 pattern that no rule matches, in canonical form.
+    Uncovered(Pattern),
+}
 
-        for row in grid {
-            result[y].push(row[y]);
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Issue::Conflict { first, second } => write!(
+                f,
+                "rule {} conflicts with rule {}, matching the same patterns",
+                second, first
+            ),
+            Issue::Duplicate { first, second } => {
+                write!(f, "rule {} is a duplicate of rule {}", second, first)
+            }
+            Issue::Uncovered(pattern) => write!(f, "no rule matches {}", pattern),
         }
     }
+}
 
-    result
+/// Enhancement rules, keyed by the canonical form of their input.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Rulebook {
+    rules: HashMap<Pattern, (usize, Pattern)>,
+    issues: Vec<Issue>,
 }
 
-fn parse_input(input: &str) -> HashMap<PixelGrid, PixelGrid> {
-    let mut map = HashMap::new();
+impl Rulebook {
+    pub fn new<I: IntoIterator<Item = (Pattern, Pattern)>>(rules: I) -> Rulebook {
+        let mut book = HashMap::new();
+        let mut issues = Vec::new();
 
-    let input = parse(input, |line| line);
+        for (i, (pattern, output)) in rules.into_iter().enumerate() {
+            let number = i + 1;
 
-    for (pattern, replace) in input {
-        let replace = || replace.clone();
+            match book.get(&pattern.canonical()) {
+                Some(&(first, existing)) => issues.push(if existing == output {
+                    Issue::Duplicate {
+                        first,
+                        second: number,
+                    }
+                } else {
+                    Issue::Conflict {
+                        first,
+                        second: number,
+                    }
+                }),
+                None => {
+                    book.insert(pattern.canonical(), (number, output));
+                }
+            }
+        }
 
-        let base = pattern;
+        for size in 2..=3 {
+            issues.extend(
+                Pattern::all(size)
+                    .filter(|&pattern| pattern == pattern.canonical())
+                    .filter(|pattern| !book.contains_key(pattern))
+                    .map(Issue::Uncovered),
+            );
+        }
 
-        let flip_vert = mirror_vertical(&base);
-        let flip_diag = mirror_diagonal(&base);
-        let flip_hori = mirror_horizontal(&base);
+        Rulebook {
+            rules: book,
+            issues,
+        }
+    }
 
-        let flip_hori_diag = mirror_diagonal(&flip_hori);
-        let flip_vert_diag = mirror_diagonal(&flip_vert);
-        let flip_hori_vert = mirror_vertical(&flip_hori);
+    /// Number of distinct rules, duplicates and conflicts left out.
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
 
-        let flip_hori_vert_diag = mirror_diagonal(&flip_hori_vert);
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
 
-        map.entry(base).or_insert_with(&replace);
-        map.entry(flip_vert).or_insert_with(&replace);
-        map.entry(flip_diag).or_insert_with(&replace);
-        map.entry(flip_hori).or_insert_with(&replace);
+    /// The output of the rule matching `pattern`, in any orientation.
+    pub fn enhance(&self, pattern: Pattern) -> Option<Pattern> {
+        self.rules
+            .get(&pattern.canonical())
+            .map(|&(_, output)| output)
+    }
 
-        map.entry(flip_hori_diag).or_insert_with(&replace);
-        map.entry(flip_hori_vert).or_insert_with(&replace);
-        map.entry(flip_vert_diag).or_insert_with(&replace);
+    pub fn issues(&self) -> &[Issue] {
+        &self.issues
+    }
 
-        map.entry(flip_hori_vert_diag).or_insert_with(&replace);
+    pub fn conflicts(&self) -> impl Iterator<Item = &Issue> {
+        self.issues
+            .iter()
+            .filter(|issue| matches!(issue, Issue::Conflict { .. }))
     }
+}
 
-    map
+fn parse_input(input: &str) -> Result<Rulebook, ParseError> {
+    let lines = parse(input, |line| line)?;
+    let mut rules = Vec::with_capacity(lines.len());
+
+    for (i, (pattern, output)) in lines.iter().enumerate() {
+        match (Pattern::from_rows(pattern), Pattern::from_rows(output)) {
+            (Some(pattern), Some(output)) => rules.push((pattern, output)),
+            _ => {
+                return Err(ParseError::new(
+                    i + 1,
+                    1,
+                    input.lines().nth(i).unwrap_or(""),
+                    ErrorKind::Invalid("patterns must be squares of 1 to 4 pixels".to_owned()),
+                ))
+            }
+        }
+    }
+
+    Ok(Rulebook::new(rules))
 }
 
 fn start_pattern() -> PixelGrid {
@@ -88,7 +295,7 @@ impl Grid {
     }
 
     fn chunks(&self) -> Vec<Vec<PixelGrid>> {
-        let chunk_size = if self.pixels.len() % 2 == 0 { 2 } else { 3 };
+        let chunk_size = if self.pixels.len().is_multiple_of(2) { 2 } else { 3 };
         let nb_chunks = self.pixels.len() / chunk_size;
 
         let mut chunks = Vec::with_capacity(nb_chunks);
@@ -118,12 +325,19 @@ impl Grid {
         chunks
     }
 
-    fn step(&mut self, map: &HashMap<PixelGrid, PixelGrid>) {
+    fn step(&mut self, rules: &Rulebook) {
         let chunks = self.chunks()
             .into_iter()
             .map(|row| {
                 row.into_iter()
-                    .map(|pattern| map[&pattern].clone())
+                    .map(|chunk| {
+                        let pattern = Pattern::from_rows(&chunk).expect("chunks are squares");
+
+                        rules
+                            .enhance(pattern)
+                            .unwrap_or_else(|| panic!("no rule matches {}", pattern))
+                            .rows()
+                    })
                     .collect::<Vec<_>>()
             })
             .collect::<Vec<_>>();
@@ -152,88 +366,307 @@ impl Grid {
     }
 }
 
-pub fn part1(input: &str) -> usize {
-    let input = parse_input(input);
+/// The art as how many times each 3×3 block appears in it, so that pixels can
+/// be counted after any number of iterations.
+///
+/// Every three iterations a 3×3 block grows into a 9×9 square, which is split
+/// into nine 3×3 blocks that evolve without seeing each other from then on.
+///
+/// # Panics
+///
+/// If the art reaches a pattern that no rule matches.
+pub struct Fractal<'a> {
+    rules: &'a Rulebook,
+    /// The nine blocks each block becomes after three iterations.
+    children: HashMap<Pattern, Vec<Pattern>>,
+    /// The pixels on after 0, 1 or 2 iterations of a block.
+    lit: HashMap<(Pattern, usize), usize>,
+}
+
+impl<'a> Fractal<'a> {
+    pub fn new(rules: &'a Rulebook) -> Fractal<'a> {
+        Fractal {
+            rules,
+            children: HashMap::new(),
+            lit: HashMap::new(),
+        }
+    }
+
+    /// The whole art after `iterations`, whose side grows by about 3^(1/3)
+    /// each time, so only for a few dozen iterations.
+    pub fn grid(&self, iterations: usize) -> PixelGrid {
+        let mut grid = Grid::new(start_pattern());
+
+        for _ in 0..iterations {
+            grid.step(self.rules);
+        }
+
+        grid.pixels
+    }
+
+    fn children(&mut self, block: Pattern) -> &[Pattern] {
+        let rules = self.rules;
+
+        self.children.entry(block).or_insert_with(|| {
+            let mut grid = Grid::new(block.rows());
+
+            for _ in 0..3 {
+                grid.step(rules);
+            }
+
+            grid.chunks()
+                .into_iter()
+                .flatten()
+                .map(|chunk| Pattern::from_rows(&chunk).expect("chunks are squares"))
+                .collect()
+        })
+    }
+
+    fn lit(&mut self, block: Pattern, iterations: usize) -> usize {
+        let rules = self.rules;
+
+        *self
+            .lit
+            .entry((block, iterations))
+            .or_insert_with(|| {
+                let mut grid = Grid::new(block.rows());
+
+                for _ in 0..iterations {
+                    grid.step(rules);
+                }
 
+                grid.nb_pixels_on()
+            })
+    }
+
+    /// How many times each block appears after `3 * rounds` iterations.
+    pub fn blocks(&mut self, rounds: usize) -> HashMap<Pattern, BigUint> {
+        let start = Pattern::from_rows(&start_pattern()).expect("the start is a 3×3 square");
+
+        let mut blocks = HashMap::new();
+        blocks.insert(start, BigUint::from(1u32));
+
+        for _ in 0..rounds {
+            let mut next = HashMap::new();
+
+            for (block, count) in blocks {
+                for child in self.children(block).to_vec() {
+                    *next.entry(child).or_insert_with(BigUint::default) += &count;
+                }
+            }
+
+            blocks = next;
+        }
+
+        blocks
+    }
+
+    pub fn pixels_on(&mut self, iterations: usize) -> BigUint {
+        self.blocks(iterations / 3)
+            .into_iter()
+            .map(|(block, count)| count * self.lit(block, iterations % 3))
+            .sum()
+    }
+}
+
+/// The art after `iterations`, every step being captured in `frames` if any.
+///
+/// # Panics
+///
+/// If the art reaches a pattern that no rule matches.
+pub fn render(
+    rules: &Rulebook,
+    iterations: usize,
+    mut frames: Option<&mut Frames>,
+) -> io::Result<Image> {
     let mut grid = Grid::new(start_pattern());
 
-    for _ in 0..5 {
-        grid.step(&input);
+    for step in 0..=iterations {
+        if step > 0 {
+            grid.step(rules);
+        }
+
+        if let Some(ref mut frames) = frames {
+            frames.capture(step, || Image::from_rows(&grid.pixels))?;
+        }
     }
 
-    grid.nb_pixels_on()
+    Ok(Image::from_rows(&grid.pixels))
 }
 
-pub fn part2(input: &str) -> usize {
-    let input = parse_input(input);
+pub fn part1(input: &Rulebook) -> BigUint {
+    Fractal::new(input).pixels_on(5)
+}
 
-    let mut grid = Grid::new(start_pattern());
+pub fn part2(input: &Rulebook) -> BigUint {
+    Fractal::new(input).pixels_on(18)
+}
 
-    for _ in 0..18 {
-        grid.step(&input);
+pub struct Day21;
+
+impl Solution for Day21 {
+    const DAY: u32 = 21;
+
+    type Input = Rulebook;
+    type Output1 = BigUint;
+    type Output2 = BigUint;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        let rules = parse_input(input.trim())?;
+
+        // Steps would have no rule for an uncovered pattern.
+        let error = rules
+            .issues()
+            .iter()
+            .find(|issue| !matches!(issue, Issue::Duplicate { .. }));
+
+        if let Some(error) = error {
+            return Err(error.to_string().into());
+        }
+
+        Ok(rules)
+    }
+
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input)
     }
 
-    grid.nb_pixels_on()
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use input;
+    use render::Palette;
+
+    fn pattern(s: &str) -> Pattern {
+        let rows = s
+            .split('/')
+            .map(|row| row.chars().map(|c| c == '#').collect())
+            .collect::<Vec<_>>();
+
+        Pattern::from_rows(&rows).unwrap()
+    }
 
     #[test]
     fn test_mirror_vertical_2() {
-        let a = vec![vec![1, 2], vec![3, 4]];
-
-        assert_eq!(mirror_vertical(&a), vec![vec![2, 1], vec![4, 3]]);
+        assert_eq!(pattern("#./..").mirror_vertical(), pattern(".#/.."));
     }
 
     #[test]
     fn test_mirror_vertical_3() {
-        let a = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]];
-
         assert_eq!(
-            mirror_vertical(&a),
-            vec![vec![3, 2, 1], vec![6, 5, 4], vec![9, 8, 7]]
+            pattern("##./#../...").mirror_vertical(),
+            pattern(".##/..#/...")
         );
     }
 
     #[test]
     fn test_mirror_horizontal_2() {
-        let a = vec![vec![1, 2], vec![3, 4]];
-
-        assert_eq!(mirror_horizontal(&a), vec![vec![3, 4], vec![1, 2]]);
+        assert_eq!(pattern("#./..").mirror_horizontal(), pattern("../#."));
     }
 
     #[test]
     fn test_mirror_horizontal_3() {
-        let a = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]];
-
         assert_eq!(
-            mirror_horizontal(&a),
-            vec![vec![7, 8, 9], vec![4, 5, 6], vec![1, 2, 3]]
+            pattern("##./#../...").mirror_horizontal(),
+            pattern(".../#../##.")
         );
     }
 
     #[test]
     fn test_mirror_diagonal_2() {
-        let a = vec![vec![1, 2], vec![3, 4]];
-
-        assert_eq!(mirror_diagonal(&a), vec![vec![1, 3], vec![2, 4]]);
+        assert_eq!(pattern("##/..").mirror_diagonal(), pattern("#./#."));
     }
 
     #[test]
     fn test_mirror_diagonal_3() {
-        let a = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]];
+        assert_eq!(
+            pattern(".#./..#/###").mirror_diagonal(),
+            pattern("..#/#.#/.##")
+        );
+    }
+
+    #[test]
+    fn symmetries() {
+        let glider = pattern(".#./..#/###");
+        let mut symmetries = glider.symmetries().to_vec();
+
+        assert_eq!(glider.rotate(), pattern("#../#.#/##."));
+        assert_eq!(glider.to_string(), ".#./..#/###");
+
+        symmetries.sort();
+        symmetries.dedup();
+        assert_eq!(symmetries.len(), 8);
+        assert!(symmetries
+            .iter()
+            .all(|p| p.canonical() == glider.canonical()));
+
+        let diagonal = pattern("#./.#");
+        let same = diagonal.symmetries().iter().filter(|&&p| p == diagonal).count();
+        assert_eq!(same, 4);
+    }
+
+    #[test]
+    fn rulebook_issues() {
+        let rules = parse_input(
+            "../.# => ##./#../...
+.#/.. => ##./#../...
+#./.. => ..#/..#/...
+.#./..#/### => #..#/..../..../#..#",
+        )
+        .unwrap();
+
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules.enhance(pattern("#./..")), Some(pattern("##./#../...")));
+        assert_eq!(
+            rules.issues()[..2],
+            [
+                Issue::Duplicate {
+                    first: 1,
+                    second: 2
+                },
+                Issue::Conflict {
+                    first: 1,
+                    second: 3
+                },
+            ]
+        );
+
+        // 6 kinds of 2×2 patterns and 102 of 3×3 ones, 2 of them covered.
+        assert_eq!(rules.issues().len(), 2 + 6 + 102 - 2);
+        assert!(rules.issues().contains(&Issue::Uncovered(pattern("../.."))));
+        assert!(!rules.issues().contains(&Issue::Uncovered(pattern("#../#.#/##."))));
 
         assert_eq!(
-            mirror_diagonal(&a),
-            vec![vec![1, 4, 7], vec![2, 5, 8], vec![3, 6, 9]]
+            Day21::parse("../.# => ##./#../...\n#./.. => ..#/..#/...")
+                .unwrap_err()
+                .to_string(),
+            "rule 2 conflicts with rule 1, matching the same patterns"
         );
+        assert_eq!(
+            Day21::parse("../.# => ##./#../...\n.#./..#/### => #..#/..../..../#..#")
+                .unwrap_err()
+                .to_string(),
+            "no rule matches ../.."
+        );
+        assert!(parse_input("../.#/.. => ##./#../...").is_err());
+    }
+
+    #[test]
+    fn full_rulebook() {
+        let rules = Day21::parse(input::embedded(21).unwrap()).unwrap();
+
+        assert_eq!(rules.issues(), &[]);
+        assert_eq!(rules.len(), 108);
     }
 
     #[test]
     fn sample_part1() {
-        let input = parse_input("../.# => ##./#../...\n.#./..#/### => #..#/..../..../#..#");
+        let input = parse_input("../.# => ##./#../...\n.#./..#/### => #..#/..../..../#..#").unwrap();
 
         let mut grid = Grid::new(start_pattern());
 
@@ -278,4 +711,48 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn render_sample() {
+        let input = parse_input("../.# => ##./#../...\n.#./..#/### => #..#/..../..../#..#").unwrap();
+
+        let image = render(&input, 2, None).unwrap();
+
+        assert_eq!(
+            image.to_ascii(&Palette::default()),
+            "##.##.\n#..#..\n......\n##.##.\n#..#..\n......\n"
+        );
+    }
+
+    #[test]
+    fn fractal_sample() {
+        let input =
+            parse_input("../.# => ##./#../...\n.#./..#/### => #..#/..../..../#..#").unwrap();
+        let mut fractal = Fractal::new(&input);
+
+        assert_eq!(fractal.pixels_on(2), BigUint::from(12u32));
+        assert_eq!(
+            Image::from_rows(&fractal.grid(2)),
+            render(&input, 2, None).unwrap()
+        );
+    }
+
+    #[test]
+    fn fractal_matches_grid() {
+        let rules = Day21::parse(input::embedded(21).unwrap()).unwrap();
+        let mut fractal = Fractal::new(&rules);
+
+        for iterations in 0..=10 {
+            let on = fractal
+                .grid(iterations)
+                .iter()
+                .map(|row| row.iter().filter(|&&on| on).count())
+                .sum::<usize>();
+
+            assert_eq!(fractal.pixels_on(iterations), BigUint::from(on));
+        }
+
+        // Far beyond what fits in memory, or in a u64.
+        assert!(fractal.pixels_on(300).bits() > 150);
+    }
 }
@@ -1,11 +1,18 @@
+use grid::{InfiniteGrid, Point};
+use parse_error::{ErrorKind, ParseError};
+use render::{Frames, Image, Palette};
+use solution::Solution;
+
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
 
-type Position = (i32, i32);
-type Grid = HashMap<Position, bool>;
-type EvolvedGrid = HashMap<Position, State>;
+pub type Position = Point;
 
-#[derive(Debug, Copy, Clone)]
-enum Direction {
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Direction {
     Up,
     Right,
     Down,
@@ -46,6 +53,15 @@ impl Direction {
         }
     }
 
+    fn turn(&mut self, turn: Turn) {
+        match turn {
+            Turn::Left => self.turn_left(),
+            Turn::Right => self.turn_right(),
+            Turn::Reverse => self.reverse(),
+            Turn::Straight => (),
+        }
+    }
+
     fn to_move(self) -> Position {
         use self::Direction::*;
 
@@ -58,160 +74,439 @@ impl Direction {
     }
 }
 
-struct Virus {
-    grid: Grid,
-    position: Position,
-    direction: Direction,
+/// The smallest image holding every known node, drawn by `index`.
+fn image<T: Clone, F: Fn(&T) -> usize>(grid: &InfiniteGrid<T>, index: F) -> Image {
+    let bounds = grid.bounds().unwrap_or_default();
+
+    Image::from_fn(
+        bounds.width() as usize,
+        bounds.height() as usize,
+        |x, y| index(grid.get((bounds.left + x as i64, bounds.top + y as i64))),
+    )
 }
 
-impl Virus {
-    fn new(grid: Grid) -> Virus {
-        Virus {
-            grid,
-            position: (0, 0),
-            direction: Direction::Up,
+/// The colours and glyphs of the node states, clean being 0, weakened 1,
+/// infected 2 and flagged 3.
+pub fn palette() -> Palette {
+    Palette::new(
+        vec![[255, 255, 255], [230, 190, 40], [0, 0, 0], [200, 30, 30]],
+        ".W#F",
+    )
+}
+
+/// How an ant turns on a node, before moving forward.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Turn {
+    Left,
+    Right,
+    Reverse,
+    Straight,
+}
+
+impl Turn {
+    fn letter(self) -> char {
+        match self {
+            Turn::Left => 'L',
+            Turn::Right => 'R',
+            Turn::Reverse => 'U',
+            Turn::Straight => 'N',
         }
     }
+}
+
+/// What happens to an ant and the node it's on, for a state of the node.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Rule {
+    pub turn: Turn,
+    /// The state the node is left in.
+    pub next: usize,
+}
+
+/// The states of the nodes and their rules, written as a line per state:
+///
+/// ```text
+/// ; state turn next
+/// .  L  #
+/// #  R  .
+/// ```
+///
+/// A state is a glyph, the first one being the state of unknown nodes. Turns
+/// are `L`eft, `R`ight, `U`-turn or `N`one. Comments start at `;`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct RuleTable {
+    glyphs: Vec<char>,
+    rules: Vec<Rule>,
+}
+
+/// The virus of part 1, flipping nodes between clean and infected.
+pub const SIMPLE: &str = ". L #
+# R .";
+
+/// The evolved virus of part 2, going through weakened and flagged nodes.
+pub const EVOLVED: &str = ". L W
+W N #
+# R F
+F U .";
+
+impl RuleTable {
+    pub fn simple() -> RuleTable {
+        SIMPLE.parse().expect("the part 1 rules are valid")
+    }
 
-    fn step(&mut self) -> bool {
-        let current_cell = self.grid.entry(self.position).or_insert(false);
-        let is_infected = *current_cell;
+    pub fn evolved() -> RuleTable {
+        EVOLVED.parse().expect("the part 2 rules are valid")
+    }
+
+    /// The glyphs of the states, in the order of the table.
+    pub fn states(&self) -> &[char] {
+        &self.glyphs
+    }
 
-        if is_infected {
-            self.direction.turn_right();
-        } else {
-            self.direction.turn_left();
+    pub fn state(&self, glyph: char) -> Option<usize> {
+        self.glyphs.iter().position(|&g| g == glyph)
+    }
+
+    pub fn glyph(&self, state: usize) -> char {
+        self.glyphs[state]
+    }
+
+    pub fn rule(&self, state: usize) -> Rule {
+        self.rules[state]
+    }
+
+    /// The glyphs of the states, coloured as in the puzzle when they are
+    /// `.`, `W`, `#` or `F`.
+    pub fn palette(&self) -> Palette {
+        let others = Palette::distinct(self.glyphs.len());
+        let puzzle = palette();
+
+        let colors = self
+            .glyphs
+            .iter()
+            .enumerate()
+            .map(|(state, &glyph)| match ".W#F".find(glyph) {
+                Some(index) => puzzle.color(index),
+                None => others.color(state),
+            })
+            .collect();
+
+        Palette::new(colors, &self.glyphs.iter().collect::<String>())
+    }
+}
+
+impl FromStr for RuleTable {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        let mut lines = Vec::new();
+
+        for (i, text) in s.lines().enumerate() {
+            let code = text.split(';').next().unwrap_or("");
+            // Each word with its column, the words being slices of `code`.
+            let words = code
+                .split_whitespace()
+                .map(|word| (word, word.as_ptr() as usize - code.as_ptr() as usize + 1))
+                .collect::<Vec<_>>();
+            let invalid = |column, reason: &str| {
+                ParseError::new(i + 1, column, text, ErrorKind::Invalid(reason.to_owned()))
+            };
+
+            let glyph = |(word, column): (&str, usize)| {
+                let mut chars = word.chars();
+
+                match (chars.next(), chars.next()) {
+                    (Some(glyph), None) => Ok((glyph, column)),
+                    _ => Err(invalid(column, &format!("invalid state `{}`", word))),
+                }
+            };
+
+            match words[..] {
+                [] => continue,
+                [state, (turn, column), next] => {
+                    let turn = match turn {
+                        "L" => Turn::Left,
+                        "R" => Turn::Right,
+                        "U" => Turn::Reverse,
+                        "N" => Turn::Straight,
+                        _ => return Err(invalid(column, &format!("invalid turn `{}`", turn))),
+                    };
+
+                    lines.push((i, text, glyph(state)?, turn, glyph(next)?));
+                }
+                _ => {
+                    let column = words
+                        .get(3)
+                        .map_or(code.trim_end().len() + 1, |&(_, column)| column);
+
+                    return Err(invalid(column, "expected a state, a turn and a next state"));
+                }
+            }
         }
 
-        *current_cell = !is_infected;
+        let glyphs = lines
+            .iter()
+            .map(|&(_, _, (state, _), _, _)| state)
+            .collect::<Vec<_>>();
+
+        let mut rules = Vec::with_capacity(lines.len());
+
+        for (n, &(i, text, (state, state_column), turn, (next, next_column))) in
+            lines.iter().enumerate()
+        {
+            let invalid = |column, reason: String| {
+                Err(ParseError::new(
+                    i + 1,
+                    column,
+                    text,
+                    ErrorKind::Invalid(reason),
+                ))
+            };
 
-        let (dx, dy) = self.direction.to_move();
+            if glyphs[..n].contains(&state) {
+                return invalid(state_column, format!("duplicate state `{}`", state));
+            }
 
-        let (ref mut x, ref mut y) = self.position;
+            match glyphs.iter().position(|&g| g == next) {
+                Some(next) => rules.push(Rule { turn, next }),
+                None => return invalid(next_column, format!("undefined state `{}`", next)),
+            }
+        }
 
-        *x += dx;
-        *y += dy;
+        if rules.is_empty() {
+            return Err(ParseError::new(
+                1,
+                1,
+                s.lines().next().unwrap_or(""),
+                ErrorKind::Invalid("no states".to_owned()),
+            ));
+        }
 
-        !is_infected
+        Ok(RuleTable { glyphs, rules })
+    }
+}
+
+impl fmt::Display for RuleTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (state, rule) in self.rules.iter().enumerate() {
+            writeln!(
+                f,
+                "{} {} {}",
+                self.glyph(state),
+                rule.turn.letter(),
+                self.glyph(rule.next)
+            )?;
+        }
+
+        Ok(())
     }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-enum State {
-    Clean,
-    Weakened,
-    Infected,
-    Flagged,
+pub struct Ant {
+    pub position: Position,
+    pub direction: Direction,
 }
 
-impl State {
-    fn next(&mut self) {
-        use self::State::*;
+impl Ant {
+    /// At the center of the map, facing up.
+    pub fn new() -> Ant {
+        Ant::at((0, 0), Direction::Up)
+    }
 
-        *self = match *self {
-            Clean => Weakened,
-            Weakened => Infected,
-            Infected => Flagged,
-            Flagged => Clean,
-        };
+    pub fn at(position: Position, direction: Direction) -> Ant {
+        Ant {
+            position,
+            direction,
+        }
     }
 }
 
-struct EvolvedVirus {
-    grid: EvolvedGrid,
-    position: Position,
-    direction: Direction,
+impl Default for Ant {
+    fn default() -> Ant {
+        Ant::new()
+    }
 }
 
-impl EvolvedVirus {
-    fn new(grid: EvolvedGrid) -> EvolvedVirus {
-        EvolvedVirus {
-            grid,
-            position: (0, 0),
-            direction: Direction::Up,
-        }
+/// A node changed by an ant.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Burst {
+    /// Index of the ant, in the order they move.
+    pub ant: usize,
+    pub position: Position,
+    pub from: usize,
+    pub to: usize,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct UnknownState(pub char);
+
+impl fmt::Display for UnknownState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the rules have no state `{}`", self.0)
     }
+}
 
-    fn step(&mut self) -> bool {
-        let current_cell = self.grid.entry(self.position).or_insert(State::Clean);
-        let state = *current_cell;
+impl Error for UnknownState {}
 
-        match state {
-            State::Clean => self.direction.turn_left(),
-            State::Infected => self.direction.turn_right(),
-            State::Flagged => self.direction.reverse(),
-            State::Weakened => (),
+/// Ants walking on a grid by a rule table, one after the other.
+pub struct Automaton<'a> {
+    rules: &'a RuleTable,
+    grid: InfiniteGrid<usize>,
+    ants: Vec<Ant>,
+}
+
+impl<'a> Automaton<'a> {
+    /// A single ant at the center of `map`.
+    pub fn new(rules: &'a RuleTable, map: &Map) -> Result<Automaton<'a>, UnknownState> {
+        let mut grid = InfiniteGrid::new(0);
+
+        for (&position, &glyph) in map {
+            grid.set(position, rules.state(glyph).ok_or(UnknownState(glyph))?);
         }
 
-        current_cell.next();
+        Ok(Automaton {
+            rules,
+            grid,
+            ants: vec![Ant::new()],
+        })
+    }
 
-        let (dx, dy) = self.direction.to_move();
+    pub fn with_ants(mut self, ants: Vec<Ant>) -> Automaton<'a> {
+        self.ants = ants;
+        self
+    }
 
-        let (ref mut x, ref mut y) = self.position;
+    pub fn ants(&self) -> &[Ant] {
+        &self.ants
+    }
 
-        *x += dx;
-        *y += dy;
+    pub fn get(&self, position: Position) -> usize {
+        *self.grid.get(position)
+    }
+
+    /// Moves every ant once, each one seeing the nodes changed by the previous
+    /// ones.
+    pub fn step<F: FnMut(Burst)>(&mut self, mut on_burst: F) {
+        for (i, ant) in self.ants.iter_mut().enumerate() {
+            let node = self.grid.get_mut(ant.position);
+            let from = *node;
+            let rule = self.rules.rule(from);
+
+            ant.direction.turn(rule.turn);
+            *node = rule.next;
+
+            on_burst(Burst {
+                ant: i,
+                position: ant.position,
+                from,
+                to: rule.next,
+            });
+
+            let (dx, dy) = ant.direction.to_move();
+            ant.position = (ant.position.0 + dx, ant.position.1 + dy);
+        }
+    }
+
+    pub fn run<F: FnMut(Burst)>(&mut self, steps: usize, mut on_burst: F) {
+        for _ in 0..steps {
+            self.step(&mut on_burst);
+        }
+    }
 
-        state == State::Weakened
+    /// The known nodes, each one drawn with the index of its state.
+    pub fn image(&self) -> Image {
+        image(&self.grid, |&state| state)
     }
 }
 
-fn parse_input(input: &str) -> Grid {
-    let height = input.lines().count() as i32;
-    let width = input.lines().next().unwrap().chars().count() as i32;
+/// The glyph of every node of the map, the center being `(0, 0)`.
+pub type Map = HashMap<Position, char>;
 
-    let mut grid = Grid::with_capacity((height * width) as usize);
+pub fn parse_map(input: &str) -> Map {
+    let height = input.lines().count() as i64;
+    let width = input.lines().next().map_or(0, |line| line.chars().count()) as i64;
+
+    let mut map = Map::with_capacity((height * width) as usize);
 
     for (y, line) in input.lines().enumerate() {
-        let y = y as i32 - height / 2;
-        for (x, c) in line.chars().enumerate() {
-            let x = x as i32 - width / 2;
-            let value = match c {
-                '.' => false,
-                '#' => true,
-                _ => unreachable!(),
-            };
+        let y = y as i64 - height / 2;
 
-            grid.insert((x, y), value);
+        for (x, glyph) in line.chars().enumerate() {
+            map.insert((x as i64 - width / 2, y), glyph);
         }
     }
 
-    grid
+    map
 }
 
-fn parse_evolved_input(input: &str) -> EvolvedGrid {
-    let height = input.lines().count() as i32;
-    let width = input.lines().next().unwrap().chars().count() as i32;
+/// The grid after `bursts`, every burst being captured in `frames` if any.
+pub fn render(
+    automaton: &mut Automaton,
+    bursts: usize,
+    mut frames: Option<&mut Frames>,
+) -> io::Result<Image> {
+    for burst in 0..=bursts {
+        if burst > 0 {
+            automaton.step(|_| ());
+        }
 
-    let mut grid = EvolvedGrid::with_capacity((height * width) as usize);
+        if let Some(ref mut frames) = frames {
+            frames.capture(burst, || automaton.image())?;
+        }
+    }
 
-    for (y, line) in input.lines().enumerate() {
-        let y = y as i32 - height / 2;
-        for (x, c) in line.chars().enumerate() {
-            let x = x as i32 - width / 2;
-            let value = match c {
-                '.' => State::Clean,
-                '#' => State::Infected,
-                _ => unreachable!(),
-            };
+    Ok(automaton.image())
+}
 
-            grid.insert((x, y), value);
+/// How many bursts infect a node, for a single ant, the rules needing a `#`
+/// state for infected nodes.
+pub fn infections(rules: &RuleTable, map: &Map, bursts: usize) -> Result<usize, UnknownState> {
+    let infected = rules.state('#').ok_or(UnknownState('#'))?;
+    let mut automaton = Automaton::new(rules, map)?;
+
+    let mut count = 0;
+    automaton.run(bursts, |burst| {
+        if burst.to == infected {
+            count += 1;
         }
-    }
+    });
 
-    grid
+    Ok(count)
 }
 
-pub fn part1(input: &str) -> usize {
-    let mut virus = Virus::new(parse_input(input));
+pub fn part1(input: &Map) -> Result<usize, UnknownState> {
+    infections(&RuleTable::simple(), input, 10_000)
+}
 
-    (0..10_000).filter(|_| virus.step()).count()
+pub fn part2(input: &Map) -> Result<usize, UnknownState> {
+    infections(&RuleTable::evolved(), input, 10_000_000)
 }
 
-pub fn part2(input: &str) -> usize {
-    let mut virus = EvolvedVirus::new(parse_evolved_input(input));
+pub struct Day22;
+
+impl Solution for Day22 {
+    const DAY: u32 = 22;
+
+    type Input = Map;
+    type Output1 = usize;
+    type Output2 = usize;
 
-    (0..10_000_000).filter(|_| virus.step()).count()
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        let map = parse_map(input.trim());
+
+        match map.values().find(|&&glyph| glyph != '.' && glyph != '#') {
+            Some(glyph) => Err(format!("Invalid node: {}", glyph).into()),
+            None => Ok(map),
+        }
+    }
+
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input).expect("the nodes are checked when parsed")
+    }
+
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input).expect("the nodes are checked when parsed")
+    }
 }
 
 #[cfg(test)]
@@ -222,65 +517,178 @@ mod tests {
 
     #[test]
     fn sample_parser() {
-        let mut sample_grid = Grid::with_capacity(9);
-        sample_grid.insert((-1, -1), false);
-        sample_grid.insert((0, -1), false);
-        sample_grid.insert((1, -1), true);
+        let mut sample_map = Map::with_capacity(9);
+        sample_map.insert((-1, -1), '.');
+        sample_map.insert((0, -1), '.');
+        sample_map.insert((1, -1), '#');
 
-        sample_grid.insert((-1, 0), true);
-        sample_grid.insert((0, 0), false);
-        sample_grid.insert((1, 0), false);
+        sample_map.insert((-1, 0), '#');
+        sample_map.insert((0, 0), '.');
+        sample_map.insert((1, 0), '.');
 
-        sample_grid.insert((-1, 1), false);
-        sample_grid.insert((0, 1), false);
-        sample_grid.insert((1, 1), false);
+        sample_map.insert((-1, 1), '.');
+        sample_map.insert((0, 1), '.');
+        sample_map.insert((1, 1), '.');
 
-        assert_eq!(parse_input(SAMPLE_INPUT), sample_grid);
+        assert_eq!(parse_map(SAMPLE_INPUT), sample_map);
+        assert!(Day22::parse("..#\n#W.\n...").is_err());
     }
 
     #[test]
-    fn sample_evolved_parser() {
-        let mut sample_grid = EvolvedGrid::with_capacity(9);
-        sample_grid.insert((-1, -1), State::Clean);
-        sample_grid.insert((0, -1), State::Clean);
-        sample_grid.insert((1, -1), State::Infected);
-
-        sample_grid.insert((-1, 0), State::Infected);
-        sample_grid.insert((0, 0), State::Clean);
-        sample_grid.insert((1, 0), State::Clean);
-
-        sample_grid.insert((-1, 1), State::Clean);
-        sample_grid.insert((0, 1), State::Clean);
-        sample_grid.insert((1, 1), State::Clean);
+    fn rule_tables() {
+        let evolved = RuleTable::evolved();
+
+        assert_eq!(evolved.states(), &['.', 'W', '#', 'F']);
+        assert_eq!(
+            evolved.rule(3),
+            Rule {
+                turn: Turn::Reverse,
+                next: 0
+            }
+        );
+        assert_eq!(evolved.to_string().parse(), Ok(evolved.clone()));
+        assert_eq!(evolved.palette(), palette());
+
+        let langton = "; Langton's ant\n. R #  ; white\n\n# L ."
+            .parse::<RuleTable>()
+            .unwrap();
+
+        assert_eq!(langton.states(), &['.', '#']);
+        assert_eq!(
+            langton.rule(0),
+            Rule {
+                turn: Turn::Right,
+                next: 1
+            }
+        );
+    }
 
-        assert_eq!(parse_evolved_input(SAMPLE_INPUT), sample_grid);
+    #[test]
+    fn rule_table_errors() {
+        let error = |table: &str| table.parse::<RuleTable>().unwrap_err().kind;
+
+        assert_eq!(
+            error(". L #\n# X ."),
+            ErrorKind::Invalid("invalid turn `X`".to_owned())
+        );
+        assert_eq!(
+            error(". L #"),
+            ErrorKind::Invalid("undefined state `#`".to_owned())
+        );
+        assert_eq!(
+            error(". L .\n. R ."),
+            ErrorKind::Invalid("duplicate state `.`".to_owned())
+        );
+        assert_eq!(
+            error(". L"),
+            ErrorKind::Invalid("expected a state, a turn and a next state".to_owned())
+        );
+        assert_eq!(error("; nothing"), ErrorKind::Invalid("no states".to_owned()));
+
+        let column = |table: &str| table.parse::<RuleTable>().unwrap_err().column;
+
+        assert_eq!(column(". L #\n#  X ."), 4);
+        assert_eq!(column(". L #"), 5);
+        assert_eq!(column(". L .\n  . R ."), 3);
+        assert_eq!(column(". L ; comment"), 4);
+        assert_eq!(column(". L . .\n"), 7);
+        assert_eq!(column(". L ab"), 5);
     }
 
     #[test]
     fn move_sample() {
-        let mut virus = Virus::new(parse_input(SAMPLE_INPUT));
+        assert_eq!(infections(&RuleTable::simple(), &parse_map(SAMPLE_INPUT), 70), Ok(41));
+    }
 
-        let nb_infections = (0..70).filter(|_| virus.step()).count();
+    #[test]
+    fn move_evolved_sample() {
+        assert_eq!(infections(&RuleTable::evolved(), &parse_map(SAMPLE_INPUT), 100), Ok(26));
+    }
 
-        assert_eq!(nb_infections, 41);
+    #[test]
+    fn unknown_states() {
+        let map = parse_map(SAMPLE_INPUT);
+        let rules = ". R x\nx L .".parse::<RuleTable>().unwrap();
+
+        assert_eq!(infections(&rules, &map, 10), Err(UnknownState('#')));
+        assert_eq!(
+            infections(&RuleTable::simple(), &parse_map("..x"), 10),
+            Err(UnknownState('x'))
+        );
     }
 
     #[test]
-    fn move_evolved_sample() {
-        let mut virus = EvolvedVirus::new(parse_evolved_input(SAMPLE_INPUT));
+    fn several_ants() {
+        let rules = RuleTable::simple();
+        let map = parse_map(SAMPLE_INPUT);
+
+        // Two ants on the same node: the second one sees it infected.
+        let mut automaton = Automaton::new(&rules, &map)
+            .unwrap()
+            .with_ants(vec![Ant::new(), Ant::new()]);
+        let mut bursts = Vec::new();
+
+        automaton.step(|burst| bursts.push(burst));
+
+        assert_eq!(
+            bursts,
+            vec![
+                Burst {
+                    ant: 0,
+                    position: (0, 0),
+                    from: 0,
+                    to: 1
+                },
+                Burst {
+                    ant: 1,
+                    position: (0, 0),
+                    from: 1,
+                    to: 0
+                },
+            ]
+        );
+        assert_eq!(
+            automaton.ants(),
+            &[
+                Ant::at((-1, 0), Direction::Left),
+                Ant::at((1, 0), Direction::Right)
+            ]
+        );
+
+        // Ants far enough apart don't meet.
+        let mut automaton = Automaton::new(&rules, &map)
+            .unwrap()
+            .with_ants(vec![Ant::new(), Ant::at((1000, 0), Direction::Up)]);
+        let mut infections = [0, 0];
+
+        automaton.run(70, |burst| {
+            if burst.to == 1 {
+                infections[burst.ant] += 1;
+            }
+        });
+
+        assert_eq!(infections, [41, super::infections(&rules, &Map::new(), 70).unwrap()]);
+    }
 
-        let nb_infections = (0..100).filter(|_| virus.step()).count();
+    #[test]
+    fn render_sample() {
+        let map = parse_map(SAMPLE_INPUT);
+        let draw = |rules: &RuleTable| {
+            let mut automaton = Automaton::new(rules, &map).unwrap();
+            render(&mut automaton, 7, None).unwrap().to_ascii(&rules.palette())
+        };
 
-        assert_eq!(nb_infections, 26);
+        assert_eq!(draw(&RuleTable::simple()), "#..#\n###.\n....\n");
+        assert_eq!(draw(&RuleTable::evolved()), "WW.#\n#.W.\n....\n");
     }
 
     #[test]
     fn part1_sample() {
-        assert_eq!(part1(SAMPLE_INPUT), 5587);
+        assert_eq!(part1(&parse_map(SAMPLE_INPUT)), Ok(5587));
     }
 
     #[test]
     fn part2_sample() {
-        assert_eq!(part2(SAMPLE_INPUT), 2511944);
+        assert_eq!(part2(&parse_map(SAMPLE_INPUT)), Ok(2511944));
     }
 }
@@ -1,103 +1,91 @@
-use day23_parser::{parse, Instruction, Value};
+use day23_parser::{parse, Instruction};
+use solution::Solution;
+use vm::optimizer::{optimize, Op};
+use vm::Machine;
 
-use std::collections::HashMap;
+use std::error::Error;
 
-struct CPU {
-    registers: HashMap<char, i64>,
-    instructions: Vec<Instruction>,
-    program_counter: usize,
-}
+type Cpu = Machine<Instruction>;
 
-impl CPU {
-    fn new(instructions: Vec<Instruction>) -> CPU {
-        CPU {
-            registers: HashMap::new(),
-            instructions,
-            program_counter: 0,
-        }
-    }
+fn run_to_end(cpu: &mut Cpu) -> u32 {
+    let mut nb_mul = 0;
 
-    fn run(&mut self, instruction: Instruction) {
-        let mut advance_pc = true;
-        match instruction {
-            Instruction::Set(reg, val) => {
-                let val = self.get_val(val);
-                self.set(reg, val);
-            }
-            Instruction::Sub(reg, val) => {
-                let val = self.get(reg) - self.get_val(val);
-                self.set(reg, val);
-            }
-            Instruction::Mul(reg, val) => {
-                let val = self.get(reg) * self.get_val(val);
-                self.set(reg, val);
-            }
-            Instruction::Jnz(val, offset) => {
-                if self.get_val(val) != 0 {
-                    self.program_counter =
-                        (self.program_counter as i64 + self.get_val(offset)) as usize;
-                    advance_pc = false;
-                }
-            }
-        }
-
-        if advance_pc {
-            self.program_counter += 1;
+    while let Ok(instruction) = cpu.step(&mut ()) {
+        if let Instruction::Mul(_, _) = instruction {
+            nb_mul += 1;
         }
     }
 
-    fn get(&self, reg: char) -> i64 {
-        *self.registers.get(&reg).unwrap_or(&0)
-    }
+    nb_mul
+}
 
-    fn set(&mut self, reg: char, value: i64) {
-        self.registers.insert(reg, value);
-    }
+pub fn part1(input: &[Instruction]) -> u32 {
+    let mut cpu = Cpu::new(input.to_vec());
 
-    fn get_val(&self, value: Value) -> i64 {
-        match value {
-            Value::Register(reg) => self.get(reg),
-            Value::Literal(val) => val,
-        }
-    }
+    run_to_end(&mut cpu)
+}
 
-    fn run_to_end(&mut self) -> u32 {
-        let mut nb_mul = 0;
+pub fn part2(input: &[Instruction]) -> i64 {
+    let mut cpu: Machine<Op> = Machine::new(optimize(input).program);
 
-        while let Some(&instruction) = self.instructions.get(self.program_counter) {
-            self.run(instruction);
+    cpu.set('a', 1);
 
-            if let Instruction::Mul(_, _) = instruction {
-                nb_mul += 1;
-            }
-        }
+    cpu.run(&mut ());
 
-        nb_mul
-    }
+    cpu.get('h')
 }
 
-pub fn part1(input: &str) -> u32 {
-    let mut cpu = CPU::new(parse(input));
-
-    cpu.run_to_end()
-}
+pub struct Day23;
 
-pub fn part2(input: &str) -> usize {
-    let mut cpu = CPU::new(parse(input).into_iter().take(8).collect());
+impl Solution for Day23 {
+    const DAY: u32 = 23;
 
-    cpu.set('a', 1);
+    type Input = Vec<Instruction>;
+    type Output1 = u32;
+    type Output2 = i64;
 
-    cpu.run_to_end();
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(parse(input.trim())?)
+    }
 
-    let from = cpu.get('b');
-    let to = cpu.get('c');
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input)
+    }
 
-    (from..to + 1)
-        .filter(|&n| (n - from) % 17 == 0)
-        .filter(|&n| not_prime(n))
-        .count()
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
+    }
 }
 
-fn not_prime(n: i64) -> bool {
-    (2..).take_while(|&d| d * d <= n).any(|d| n % d == 0)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use day23_parser::{assemble, disassemble};
+    use input;
+
+    #[test]
+    fn disassembly_round_trip() {
+        let program = parse(input::embedded(23).unwrap().trim()).unwrap();
+
+        assert_eq!(assemble(&disassemble(&program)), Ok(program));
+    }
+
+    #[test]
+    fn optimizer_rewrites() {
+        let program = parse(input::embedded(23).unwrap().trim()).unwrap();
+
+        let rewrites = optimize(&program)
+            .rewrites
+            .iter()
+            .map(|r| r.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            rewrites,
+            vec![
+                "10-23: nested counting loops: f = 0 if d * e == b for d and e up to b",
+                "11-19: divisibility test: f = 0 if d * e == b for e up to b",
+            ]
+        );
+    }
 }
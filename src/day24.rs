@@ -1,180 +1,334 @@
+use rayon::prelude::*;
+use solution::Solution;
+
+use std::collections::HashMap;
+use std::error::Error;
 use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
-struct Component(u32, u32);
+pub struct Component(u32, u32);
 
 impl Component {
-    fn new(a: u32, b: u32) -> Component {
+    pub fn new(a: u32, b: u32) -> Component {
         Component(a, b)
     }
 
-    fn from_str(comp: &str) -> Component {
-        let numbers: Vec<u32> = comp.split('/').map(|n| n.parse().unwrap()).collect();
-
-        Component::new(numbers[0], numbers[1])
+    fn value(&self) -> u64 {
+        u64::from(self.0) + u64::from(self.1)
     }
 
-    fn value(&self) -> u32 {
-        self.0 + self.1
+    /// The port at the other end from one with `pins`.
+    fn other_end(&self, pins: u32) -> u32 {
+        if self.0 == pins {
+            self.1
+        } else {
+            self.0
+        }
     }
 
-    fn has_pin(&self, nb_pin: u32) -> bool {
-        self.0 == nb_pin || self.1 == nb_pin
+    fn reversed(self) -> Component {
+        Component(self.1, self.0)
     }
 }
 
-#[derive(Clone)]
-struct Bridge<'a> {
-    components: &'a [Component],
-    elements: Vec<(usize, bool)>,
-}
+impl FromStr for Component {
+    type Err = Box<dyn Error>;
 
-impl<'a> Bridge<'a> {
-    fn new(comp: &'a [Component]) -> Bridge<'a> {
-        Bridge {
-            components: comp,
-            elements: Vec::new(),
+    fn from_str(comp: &str) -> Result<Self, Self::Err> {
+        let mut ports = comp.split('/');
+
+        match (ports.next(), ports.next(), ports.next()) {
+            (Some(a), Some(b), None) => Ok(Component::new(a.parse()?, b.parse()?)),
+            _ => Err(format!("Invalid component: {}", comp).into()),
         }
     }
+}
 
-    fn last_pin(&self) -> u32 {
-        self.elements
-            .last()
-            .map(|&(i, rev)| (self.components[i], rev))
-            .map(|(c, rev)| if rev { c.0 } else { c.1 })
-            .unwrap_or(0)
+impl fmt::Display for Component {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.0, self.1)
     }
+}
 
-    fn add(&mut self, index: usize) {
-        let last_pin = self.last_pin();
-        let comp = self.components[index];
+/// Components connected from the port of type 0, each one turned so that its
+/// first port matches the previous one.
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub struct Bridge {
+    components: Vec<Component>,
+}
 
-        assert!(comp.has_pin(last_pin));
+impl Bridge {
+    /// The bridge made of `components[i]` for each `i` of `path`, in order.
+    fn from_path(components: &[Component], path: &[usize]) -> Bridge {
+        let mut pins = 0;
 
-        let reversed = comp.1 == last_pin;
+        let components = path
+            .iter()
+            .map(|&i| {
+                let component = components[i];
+                let turned = if component.0 == pins {
+                    component
+                } else {
+                    component.reversed()
+                };
+
+                pins = turned.1;
+                turned
+            })
+            .collect();
+
+        Bridge { components }
+    }
 
-        self.elements.push((index, reversed))
+    pub fn components(&self) -> &[Component] {
+        &self.components
     }
 
-    fn value(&self) -> u32 {
-        self.elements
-            .iter()
-            .map(|&(i, _)| self.components[i])
-            .map(|c| c.value())
-            .sum()
+    pub fn len(&self) -> usize {
+        self.components.len()
     }
 
-    fn contains(&self, index: usize) -> bool {
-        self.elements.iter().any(|&(i, _)| i == index)
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
     }
 
-    fn len(&self) -> usize {
-        self.elements.len()
+    pub fn strength(&self) -> u64 {
+        self.components.iter().map(Component::value).sum()
     }
 }
 
-impl<'a> fmt::Debug for Bridge<'a> {
+impl fmt::Display for Bridge {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let bridge = self.elements
+        let components = self
+            .components
             .iter()
-            .map(|&(i, rev)| (self.components[i], rev))
-            .map(|(c, rev)| if rev { (c.1, c.0) } else { (c.0, c.1) })
-            .map(|(a, b)| format!("{}/{}", a, b))
+            .map(|component| component.to_string())
             .collect::<Vec<_>>();
 
-        write!(f, "Bridge [ {} ]", bridge.join("--"))
+        f.write_str(&components.join("--"))
+    }
+}
+
+/// A set of component indices.
+#[derive(Clone)]
+struct BitSet(Vec<u64>);
+
+impl BitSet {
+    fn new(len: usize) -> BitSet {
+        BitSet(vec![0; len.div_ceil(64)])
+    }
+
+    fn contains(&self, i: usize) -> bool {
+        self.0[i / 64] & 1 << (i % 64) != 0
+    }
+
+    fn toggle(&mut self, i: usize) {
+        self.0[i / 64] ^= 1 << (i % 64);
     }
 }
 
-pub fn part1(input: &str) -> u32 {
-    let components = input.lines().map(Component::from_str).collect::<Vec<_>>();
+/// The strongest bridge of each length, as the indices of its components.
+type Best = Vec<Option<(u64, Vec<usize>)>>;
 
-    let mut bridges = vec![Bridge::new(&components)];
+/// A depth first walk through every bridge starting with some components.
+struct Search<'a> {
+    components: &'a [Component],
+    /// The components with a port of each number of pins.
+    by_pins: &'a HashMap<u32, Vec<usize>>,
+    used: BitSet,
+    path: Vec<usize>,
+    strength: u64,
+    best: Best,
+}
 
-    loop {
-        let mut new_bridges = Vec::new();
-        let mut has_new = false;
+impl<'a> Search<'a> {
+    fn new(components: &'a [Component], by_pins: &'a HashMap<u32, Vec<usize>>) -> Search<'a> {
+        Search {
+            components,
+            by_pins,
+            used: BitSet::new(components.len()),
+            path: Vec::new(),
+            strength: 0,
+            best: vec![None; components.len() + 1],
+        }
+    }
+
+    fn record(&mut self) {
+        let current = self.strength;
+        let best = &mut self.best[self.path.len()];
+
+        if best
+            .as_ref()
+            .is_none_or(|&(strength, _)| current > strength)
+        {
+            *best = Some((current, self.path.clone()));
+        }
+    }
 
-        for bridge in bridges {
-            let last_pin = bridge.last_pin();
-            let mut preserve = true;
+    /// Adds component `i` after a port with `pins`, then explores from there.
+    fn extend(&mut self, i: usize, pins: u32) {
+        let component = self.components[i];
 
-            for (i, _) in components
-                .iter()
-                .enumerate()
-                .filter(|&(i, c)| !bridge.contains(i) && c.has_pin(last_pin))
-            {
-                let mut new_bridge = bridge.clone();
+        self.used.toggle(i);
+        self.path.push(i);
+        self.strength += component.value();
 
-                new_bridge.add(i);
+        self.record();
 
-                new_bridges.push(new_bridge);
+        let next = component.other_end(pins);
 
-                preserve = false;
-                has_new = true;
+        for &j in self.by_pins.get(&next).map_or(&[][..], |v| &v[..]) {
+            if !self.used.contains(j) {
+                self.extend(j, next);
             }
+        }
 
-            if preserve {
-                new_bridges.push(bridge)
+        self.strength -= component.value();
+        self.path.pop();
+        self.used.toggle(i);
+    }
+}
+
+/// Keeps the stronger bridge of each length, the first one on ties.
+fn merge(mut first: Best, second: Best) -> Best {
+    for (mine, theirs) in first.iter_mut().zip(second) {
+        if let Some((strength, path)) = theirs {
+            if mine.as_ref().is_none_or(|&(best, _)| strength > best) {
+                *mine = Some((strength, path));
             }
         }
+    }
 
-        bridges = new_bridges;
+    first
+}
+
+/// The bridges that no other one beats on both length and strength, by
+/// increasing length and so decreasing strength.
+///
+/// Each component fitting the port of type 0 starts a branch of the search,
+/// explored on its own thread.
+pub fn pareto_front(components: &[Component]) -> Vec<Bridge> {
+    let mut by_pins = HashMap::new();
+
+    for (i, component) in components.iter().enumerate() {
+        for &pins in &[component.0, component.1] {
+            let fitting = by_pins.entry(pins).or_insert_with(Vec::new);
+
+            // A component with two equal ports is listed once.
+            if fitting.last() != Some(&i) {
+                fitting.push(i);
+            }
+        }
+    }
 
-        if !has_new {
-            return bridges
-                .iter()
-                .map(|b| b.value())
-                .max()
-                .expect("Failed to compute max bridge value");
+    let mut empty = Search::new(components, &by_pins);
+    empty.record();
+
+    let best = by_pins
+        .get(&0)
+        .map_or(&[][..], |v| &v[..])
+        .par_iter()
+        .map(|&i| {
+            let mut search = Search::new(components, &by_pins);
+            search.extend(i, 0);
+            search.best
+        })
+        .reduce(|| vec![None; components.len() + 1], merge);
+
+    let best = merge(empty.best, best);
+
+    let mut front = Vec::new();
+    let mut strongest = None;
+
+    for (strength, path) in best.into_iter().rev().flatten() {
+        if strongest.is_none_or(|strongest| strength > strongest) {
+            strongest = Some(strength);
+            front.push(Bridge::from_path(components, &path));
         }
     }
+
+    front.reverse();
+    front
 }
 
-pub fn part2(input: &str) -> u32 {
-    let components = input.lines().map(Component::from_str).collect::<Vec<_>>();
+/// The components, and their Pareto front once searched for.
+#[derive(Debug, Default)]
+pub struct Inventory {
+    components: Vec<Component>,
+    front: OnceLock<Vec<Bridge>>,
+}
 
-    let mut bridges = vec![Bridge::new(&components)];
+impl Inventory {
+    pub fn new(components: Vec<Component>) -> Inventory {
+        Inventory {
+            components,
+            front: OnceLock::new(),
+        }
+    }
 
-    loop {
-        let mut new_bridges = Vec::new();
-        let mut has_new = false;
+    pub fn components(&self) -> &[Component] {
+        &self.components
+    }
 
-        for bridge in bridges {
-            let last_pin = bridge.last_pin();
-            let mut preserve = true;
+    /// The Pareto front of the bridges, searched for on the first call only.
+    pub fn front(&self) -> &[Bridge] {
+        self.front.get_or_init(|| pareto_front(&self.components))
+    }
 
-            for (i, _) in components
-                .iter()
-                .enumerate()
-                .filter(|&(i, c)| !bridge.contains(i) && c.has_pin(last_pin))
-            {
-                let mut new_bridge = bridge.clone();
+    /// The strongest bridge, the longest one on ties.
+    pub fn strongest(&self) -> &Bridge {
+        self.front()
+            .first()
+            .expect("the empty bridge is always there")
+    }
 
-                new_bridge.add(i);
+    /// The longest bridge, the strongest one on ties.
+    pub fn longest(&self) -> &Bridge {
+        self.front()
+            .last()
+            .expect("the empty bridge is always there")
+    }
+}
 
-                new_bridges.push(new_bridge);
+impl FromStr for Inventory {
+    type Err = Box<dyn Error>;
 
-                preserve = false;
-                has_new = true;
-            }
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Inventory::new(
+            s.lines().map(str::parse).collect::<Result<_, _>>()?,
+        ))
+    }
+}
 
-            if preserve {
-                new_bridges.push(bridge)
-            }
-        }
+pub fn part1(input: &Inventory) -> u64 {
+    input.strongest().strength()
+}
 
-        bridges = new_bridges;
+pub fn part2(input: &Inventory) -> u64 {
+    input.longest().strength()
+}
 
-        if !has_new {
-            bridges.sort_by(|a, b| {
-                b.len()
-                    .cmp(&a.len())
-                    .then_with(|| b.value().cmp(&a.value()))
-            });
+pub struct Day24;
 
-            return bridges[0].value();
-        }
+impl Solution for Day24 {
+    const DAY: u32 = 24;
+
+    type Input = Inventory;
+    type Output1 = u64;
+    type Output2 = u64;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        input.trim().parse()
+    }
+
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input)
+    }
+
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
     }
 }
 
@@ -184,23 +338,73 @@ mod tests {
 
     const SAMPLE_INPUT: &str = "0/2\n2/2\n2/3\n3/4\n3/5\n0/1\n10/1\n9/10";
 
+    fn sample() -> Inventory {
+        SAMPLE_INPUT.parse().unwrap()
+    }
+
     #[test]
     fn test_parse() {
-        assert_eq!(Component::from_str("0/2"), Component::new(0, 2));
+        assert_eq!("0/2".parse::<Component>().unwrap(), Component::new(0, 2));
+        assert!("0/2/4".parse::<Component>().is_err());
+        assert!("0/x".parse::<Component>().is_err());
     }
 
     #[test]
     fn test_value() {
         assert_eq!(Component::new(3, 7).value(), 10);
+        assert_eq!(
+            Component::new(4_000_000_000, 4_000_000_000).value(),
+            8_000_000_000
+        );
     }
 
     #[test]
     fn part1_sample() {
-        assert_eq!(part1(SAMPLE_INPUT), 31);
+        assert_eq!(part1(&sample()), 31);
     }
 
     #[test]
     fn part2_sample() {
-        assert_eq!(part2(SAMPLE_INPUT), 19);
+        assert_eq!(part2(&sample()), 19);
+    }
+
+    #[test]
+    fn front_sample() {
+        let inventory = sample();
+        let front = inventory
+            .front()
+            .iter()
+            .map(|bridge| (bridge.len(), bridge.strength(), bridge.to_string()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            front,
+            vec![
+                (3, 31, "0/1--1/10--10/9".to_owned()),
+                (4, 19, "0/2--2/2--2/3--3/5".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_bridge() {
+        let inventory = "1/2\n2/3".parse::<Inventory>().unwrap();
+
+        assert_eq!(inventory.front(), &[Bridge::default()]);
+        assert_eq!(part1(&inventory), 0);
+    }
+
+    #[test]
+    fn large_pins() {
+        let inventory = "0/2000000000\n2000000000/7".parse::<Inventory>().unwrap();
+
+        assert_eq!(part1(&inventory), 2_000_000_000 * 2 + 7);
+
+        let inventory = "0/4000000000\n4000000000/4000000000"
+            .parse::<Inventory>()
+            .unwrap();
+
+        assert_eq!(part1(&inventory), 12_000_000_000);
+        assert_eq!(part2(&inventory), 12_000_000_000);
     }
 }
@@ -1,6 +1,10 @@
 use std::collections::HashMap;
+use std::convert::Infallible;
+use std::error::Error;
 
-use day25_parser::parse;
+use day25_parser::{parse, ParseError};
+use grid::InfiniteGrid;
+use solution::Solution;
 
 #[derive(Debug, Copy, Clone)]
 enum Direction {
@@ -30,7 +34,7 @@ impl Cursor {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
 struct Value(bool);
 
 impl Value {
@@ -47,20 +51,27 @@ impl Value {
     }
 }
 
-#[derive(Debug, Default)]
-struct Tape(HashMap<Cursor, Value>);
+/// The slots of the tape, on the first row of a grid.
+#[derive(Debug, Clone)]
+struct Tape(InfiniteGrid<Value>);
+
+impl Default for Tape {
+    fn default() -> Tape {
+        Tape(InfiniteGrid::row(Value::default()))
+    }
+}
 
 impl Tape {
-    fn get(&mut self, cursor: Cursor) -> Value {
-        *self.0.entry(cursor).or_insert(Value(false))
+    fn get(&self, cursor: Cursor) -> Value {
+        *self.0.get((cursor.0, 0))
     }
 
     fn set(&mut self, cursor: Cursor, value: Value) {
-        self.0.insert(cursor, value);
+        self.0.set((cursor.0, 0), value);
     }
 
     fn checksum(&self) -> usize {
-        self.0.iter().filter(|&(_, &v)| v.is_on()).count()
+        self.0.iter().filter(|&(_, v)| v.is_on()).count()
     }
 }
 
@@ -84,7 +95,7 @@ impl InstructionList {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Instruction([InstructionList; 2]);
 
 impl Instruction {
@@ -93,7 +104,7 @@ impl Instruction {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Blueprint(HashMap<State, Instruction>);
 
 impl Blueprint {
@@ -102,8 +113,8 @@ impl Blueprint {
     }
 }
 
-#[derive(Debug)]
-struct TuringMachine {
+#[derive(Debug, Clone)]
+pub struct TuringMachine {
     tape: Tape,
     cursor: Cursor,
     state: State,
@@ -135,15 +146,14 @@ impl TuringMachine {
     }
 }
 
-fn parse_input(input: &str) -> TuringMachine {
+fn parse_input(input: &str) -> Result<TuringMachine, ParseError> {
     parse(
         input,
-        |(state, (cond_a, (w_a, d_a, s_a)), (cond_b, (w_b, d_b, s_b)))| {
+        |(state, (cond_a, (w_a, d_a, s_a)), (_, (w_b, d_b, s_b)))| {
             let a = InstructionList::new(Value(w_a), Direction::from(d_a), State(s_a));
             let b = InstructionList::new(Value(w_b), Direction::from(d_b), State(s_b));
 
-            assert_ne!(cond_a, cond_b);
-
+            // The parser rejects states with two instructions for a value.
             let list = if cond_a { [b, a] } else { [a, b] };
 
             (state, Instruction(list))
@@ -168,12 +178,36 @@ fn parse_input(input: &str) -> TuringMachine {
     )
 }
 
-pub fn part1(input: &str) -> usize {
-    let mut turing_machine = parse_input(input);
+pub fn part1(input: &TuringMachine) -> usize {
+    let mut turing_machine = input.clone();
 
     turing_machine.run_to_checksum()
 }
 
-pub fn part2(_input: &str) -> ! {
+pub fn part2(_input: &TuringMachine) -> ! {
     unimplemented!()
 }
+
+pub struct Day25;
+
+impl Solution for Day25 {
+    const DAY: u32 = 25;
+
+    const HAS_PART2: bool = false;
+
+    type Input = TuringMachine;
+    type Output1 = usize;
+    type Output2 = Infallible;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(parse_input(input.trim())?)
+    }
+
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input)
+    }
+
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
+    }
+}
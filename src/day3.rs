@@ -1,243 +1,230 @@
-struct SpiralGenerator {
-    index: u32,
-    previous_last: u32,
-}
-
-impl SpiralGenerator {
-    fn new() -> SpiralGenerator {
-        SpiralGenerator {
-            index: 0,
-            previous_last: 0,
-        }
+//! Spiral memory: squares numbered from 1 at the origin, going right then
+//! counterclockwise, at `Point`s so `y` grows downwards.
+//!
+//! ```text
+//! 17  16  15  14  13
+//! 18   5   4   3  12
+//! 19   6   1   2  11
+//! 20   7   8   9  10
+//! 21  22  23---> ...
+//! ```
+
+pub use grid::Point;
+
+use grid::InfiniteGrid;
+use solution::Solution;
+
+use std::error::Error;
+use std::ops::RangeInclusive;
+
+/// The largest `r` such that `r * r <= n`.
+fn isqrt(n: u64) -> u64 {
+    let mut r = (n as f64).sqrt() as u64;
+
+    while r * r > n {
+        r -= 1;
     }
-}
-
-impl Iterator for SpiralGenerator {
-    type Item = Ring;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let index = self.index;
-
-        let first = self.previous_last + 1;
-        let last = u32::max(first, self.previous_last + 8 * index);
-
-        self.previous_last = last;
-        self.index += 1;
-
-        Some(Ring { index, first, last })
+    while (r + 1) * (r + 1) <= n {
+        r += 1;
     }
-}
 
-#[derive(Debug, Eq, PartialEq)]
-pub struct Ring {
-    pub index: u32,
-    pub first: u32,
-    pub last: u32,
+    r
 }
 
-impl Ring {
-    fn contains(&self, number: u32) -> bool {
-        number >= self.first && number <= self.last
-    }
-
-    fn group(&self, number: u32) -> u32 {
-        assert!(self.contains(number));
-        assert!(self.index > 0);
-
-        let number = number - self.first;
-
-        number / (2 * self.index)
+/// The coordinates of a square, in constant time.
+///
+/// # Panics
+///
+/// If `square` is 0.
+pub fn position(square: u64) -> Point {
+    let ring = Ring::containing(square);
+    let k = ring.index as i64;
+
+    if k == 0 {
+        return (0, 0);
     }
 
-    fn pivot(&self, number: u32) -> u32 {
-        let group = self.group(number);
+    let side = 2 * k;
+    // Counting back from the last square, at the bottom right corner.
+    let t = (ring.last - square) as i64;
 
-        self.first + (self.index - 1) + 2 * self.index * group
+    match t / side {
+        0 => (k - t, k),
+        1 => (-k, k - (t - side)),
+        2 => (-k + (t - 2 * side), -k),
+        _ => (k, -k + (t - 3 * side)),
     }
 }
 
-fn find_ring(number: u32) -> Ring {
-    assert!(number > 0);
+/// The square at a position, in constant time.
+pub fn square((x, y): Point) -> u64 {
+    let k = x.abs().max(y.abs());
 
-    let mut generator = SpiralGenerator::new();
-
-    generator
-        .find(|r| r.contains(number))
-        .expect(&format!("Failed to find ring for number {}", number))
-}
+    if k == 0 {
+        return 1;
+    }
 
-pub fn part1(input: u32) -> u32 {
-    assert!(input > 0);
+    let side = 2 * k;
+    let last = (2 * k + 1) * (2 * k + 1);
 
-    if input == 1 {
-        0
+    let t = if y == k && x > -k {
+        k - x
+    } else if x == -k && y > -k {
+        side + (k - y)
+    } else if y == -k && x < k {
+        2 * side + (x + k)
     } else {
-        let ring = find_ring(input);
-
-        let pivot = ring.pivot(input);
+        3 * side + (k + y)
+    };
 
-        ring.index + diff(input, pivot)
-    }
+    (last - t) as u64
 }
 
-fn diff(a: u32, b: u32) -> u32 {
-    if a > b {
-        a - b
-    } else {
-        b - a
-    }
-}
+/// Manhattan distance from a square to square 1.
+pub fn distance(square: u64) -> u64 {
+    let (x, y) = position(square);
 
-struct SumRing {
-    previous: Option<Box<SumRing>>,
-    ring: Ring,
-    values: Vec<u32>,
+    (x.abs() + y.abs()) as u64
 }
 
-impl SumRing {
-    fn new(mut previous: SumRing, ring: Ring) -> SumRing {
-        previous.previous = None;
+/// The squares at the same distance from the origin on both axes, the last one
+/// being `(2 * index + 1)^2`.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Ring {
+    pub index: u64,
+    pub first: u64,
+    pub last: u64,
+}
 
-        let capacity = 8 * ring.index as usize;
+impl Ring {
+    pub fn new(index: u64) -> Ring {
+        let last = (2 * index + 1) * (2 * index + 1);
 
-        SumRing {
-            previous: Some(Box::new(previous)),
-            ring,
-            values: vec![0; capacity],
+        Ring {
+            index,
+            first: if index == 0 { 1 } else { last - 8 * index + 1 },
+            last,
         }
     }
 
-    fn get(&self, group: usize, index: usize) -> Option<u32> {
-        assert!(group < 4);
-
-        let ring_index = self.ring.index as usize;
-
-        if index < 2 * ring_index {
-            let group_index = 2 * ring_index * group;
+    /// # Panics
+    ///
+    /// If `square` is 0.
+    pub fn containing(square: u64) -> Ring {
+        assert!(square > 0, "squares are numbered from 1");
 
-            Some(self.values[group_index + index])
-        } else {
-            None
-        }
+        // The smallest k such that (2k + 1)^2 >= square, i.e. 2k + 1 > the root
+        // of square - 1.
+        Ring::new(isqrt(square - 1).div_ceil(2))
     }
 
-    fn get_last(&self, group: usize) -> u32 {
-        assert!(group < 4);
-
-        let next_group_index = 2 * self.ring.index as usize * (group + 1);
+    pub fn contains(&self, square: u64) -> bool {
+        square >= self.first && square <= self.last
+    }
 
-        self.values[next_group_index - 1]
+    pub fn squares(&self) -> RangeInclusive<u64> {
+        self.first..=self.last
     }
 
-    fn compute(&mut self, x: usize) -> u32 {
-        let mut sum = 0;
+    /// The squares of the ring, with their positions.
+    pub fn iter(&self) -> impl Iterator<Item = (u64, Point)> {
+        self.squares().map(|square| (square, position(square)))
+    }
+}
 
-        let n = self.ring.index as usize;
-        let group_len = 2 * n;
+/// Squares filled in order, each one from the values of its neighbours
+/// already filled.
+pub struct Fill<T, F> {
+    values: InfiniteGrid<Option<T>>,
+    aggregate: F,
+    next: u64,
+}
 
-        let group = x / group_len;
-        let group_index = x % group_len;
+impl<T: Clone, F: FnMut(&[&T]) -> T> Fill<T, F> {
+    /// Puts `first` in square 1, then `aggregate(neighbours)` in each square.
+    pub fn new(first: T, aggregate: F) -> Fill<T, F> {
+        let mut values = InfiniteGrid::new(None);
+        values.set((0, 0), Some(first));
 
-        // add previous value
-        if let Some(prev) = x.checked_sub(1) {
-            sum += self.values[prev];
+        Fill {
+            values,
+            aggregate,
+            next: 1,
         }
+    }
 
-        // if it's the first value of a group,
-        // add a value before
-        if group_index == 0 {
-            if let Some(prev) = x.checked_sub(2) {
-                sum += self.values[prev];
-            }
-        }
+    /// The value of a square already filled.
+    pub fn get(&self, square: u64) -> Option<&T> {
+        self.values.get(position(square)).as_ref()
+    }
+}
 
-        // if it's one of the two last values of a ring,
-        // add the first value from the ring
-        if 8 * n - x <= 2 {
-            sum += self.values[0];
-        }
+impl<T: Clone, F: FnMut(&[&T]) -> T> Iterator for Fill<T, F> {
+    type Item = T;
 
-        if let Some(ref previous) = self.previous {
-            for offset in (0..3).rev() {
-                if let Some(i) = group_index.checked_sub(offset) {
-                    if let Some(val) = previous.get(group, i) {
-                        sum += val;
-                    }
-                }
-            }
+    fn next(&mut self) -> Option<T> {
+        let square = self.next;
+        self.next += 1;
 
-            if group_index < 2 {
-                let previous_group = group.checked_sub(1).unwrap_or(3);
+        let (x, y) = position(square);
 
-                sum += previous.get_last(previous_group);
-            }
-        }
+        if square > 1 {
+            let values = &self.values;
+            let neighbours = (-1..=1)
+                .flat_map(|dx| (-1..=1).map(move |dy| (x + dx, y + dy)))
+                .filter_map(|p| values.get(p).as_ref())
+                .collect::<Vec<_>>();
 
-        self.values[x] = sum;
+            let value = (self.aggregate)(&neighbours);
 
-        sum
-    }
+            self.values.set((x, y), Some(value));
+        }
 
-    fn capacity(&self) -> usize {
-        8 * self.ring.index as usize
+        self.values.get((x, y)).clone()
     }
 }
 
-fn first_sum() -> SumRing {
-    SumRing {
-        previous: None,
-        ring: Ring {
-            index: 1,
-            first: 2,
-            last: 9,
-        },
-        values: vec![1, 2, 4, 5, 10, 11, 23, 25],
-    }
+pub fn part1(input: u64) -> u64 {
+    distance(input)
 }
 
-pub fn part2(input: u32) -> u32 {
-    assert!(input > 25);
-
-    let generator = SpiralGenerator::new();
+/// The first value of the stress test larger than `input`.
+pub fn part2(input: u64) -> u64 {
+    Fill::new(1, |neighbours: &[&u64]| neighbours.iter().cloned().sum())
+        .find(|&value| value > input)
+        .expect("the values grow without bound")
+}
 
-    let mut previous_sum = Some(first_sum());
+pub struct Day3;
 
-    for ring in generator.skip(2) {
-        let sum = {
-            let mut new_sum = SumRing::new(previous_sum.take().unwrap(), ring);
+impl Solution for Day3 {
+    const DAY: u32 = 3;
 
-            for x in 0..new_sum.capacity() {
-                let value = new_sum.compute(x);
-                if value > input {
-                    return value;
-                }
-            }
+    type Input = u64;
+    type Output1 = u64;
+    type Output2 = u64;
 
-            new_sum
-        };
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        match input.trim().parse()? {
+            0 => Err("Invalid square: 0, squares are numbered from 1".into()),
+            square => Ok(square),
+        }
+    }
 
-        previous_sum = Some(sum);
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(*input)
     }
 
-    panic!("No value found")
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(*input)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    const FIRST_RING: Ring = Ring {
-        index: 0,
-        first: 1,
-        last: 1,
-    };
-
-    const SECOND_RING: Ring = Ring {
-        index: 1,
-        first: 2,
-        last: 9,
-    };
-
     const THIRD_RING: Ring = Ring {
         index: 2,
         first: 10,
@@ -245,36 +232,40 @@ mod tests {
     };
 
     #[test]
-    fn first_rings() {
-        let mut generator = SpiralGenerator::new();
-
-        // First ring: only 1
-        assert_eq!(generator.next(), Some(FIRST_RING));
-
-        // Second ring: 2 - 9
-        assert_eq!(generator.next(), Some(SECOND_RING));
-
-        // Third ring: 10 - 25
-        assert_eq!(generator.next(), Some(THIRD_RING));
-    }
-
-    #[test]
-    fn find_rings() {
-        assert_eq!(find_ring(1), FIRST_RING);
-        assert_eq!(find_ring(12), THIRD_RING);
-        assert_eq!(find_ring(23), THIRD_RING);
+    fn rings() {
+        assert_eq!(Ring::new(0), Ring { index: 0, first: 1, last: 1 });
+        assert_eq!(Ring::new(1), Ring { index: 1, first: 2, last: 9 });
+        assert_eq!(Ring::new(2), THIRD_RING);
+
+        assert_eq!(Ring::containing(1), Ring::new(0));
+        assert_eq!(Ring::containing(9), Ring::new(1));
+        assert_eq!(Ring::containing(10), THIRD_RING);
+        assert_eq!(Ring::containing(25), THIRD_RING);
+        assert!((1..10_000).all(|n| Ring::containing(n).contains(n)));
     }
 
     #[test]
-    fn groups() {
-        assert_eq!(THIRD_RING.group(12), 0);
-        assert_eq!(THIRD_RING.group(23), 3);
+    fn positions() {
+        assert_eq!(position(1), (0, 0));
+        assert_eq!(position(2), (1, 0));
+        assert_eq!(position(5), (-1, -1));
+        assert_eq!(position(9), (1, 1));
+        assert_eq!(position(12), (2, -1));
+        assert_eq!(position(23), (0, 2));
+
+        assert!((1..10_000).all(|n| square(position(n)) == n));
+        assert_eq!(square(position(1 << 50)), 1 << 50);
     }
 
     #[test]
-    fn pivots() {
-        assert_eq!(THIRD_RING.pivot(12), 11);
-        assert_eq!(THIRD_RING.pivot(23), 23);
+    fn ring_iteration() {
+        let corners = Ring::containing(4)
+            .iter()
+            .filter(|&(_, (x, y))| x.abs() == y.abs())
+            .map(|(square, _)| square)
+            .collect::<Vec<_>>();
+
+        assert_eq!(corners, vec![3, 5, 7, 9]);
     }
 
     #[test]
@@ -302,24 +293,39 @@ mod tests {
     }
 
     #[test]
-    fn second_sum() {
-        let first_sum = first_sum();
-
-        let mut second_sum = SumRing::new(first_sum, THIRD_RING);
-
-        assert_eq!(second_sum.compute(0), 26);
-        assert_eq!(second_sum.compute(1), 54);
-        assert_eq!(second_sum.compute(2), 57);
-        assert_eq!(second_sum.compute(3), 59);
-        assert_eq!(second_sum.compute(4), 122);
-        assert_eq!(second_sum.compute(5), 133);
-        assert_eq!(second_sum.compute(6), 142);
-        assert_eq!(second_sum.compute(7), 147);
-        assert_eq!(second_sum.compute(8), 304);
-        assert_eq!(second_sum.compute(9), 330);
-        assert_eq!(second_sum.compute(10), 351);
-        assert_eq!(second_sum.compute(11), 362);
-        assert_eq!(second_sum.compute(12), 747);
-        assert_eq!(second_sum.compute(13), 806);
+    fn parse() {
+        assert_eq!(Day3::parse("1024\n").unwrap(), 1024);
+        assert_eq!(
+            Day3::parse("0").unwrap_err().to_string(),
+            "Invalid square: 0, squares are numbered from 1"
+        );
+    }
+
+    #[test]
+    fn stress_test() {
+        let values = Fill::new(1, |neighbours: &[&u64]| neighbours.iter().cloned().sum())
+            .take(23)
+            .collect::<Vec<u64>>();
+
+        assert_eq!(
+            values,
+            vec![
+                1, 1, 2, 4, 5, 10, 11, 23, 25, 26, 54, 57, 59, 122, 133, 142, 147, 304, 330, 351,
+                362, 747, 806,
+            ]
+        );
+
+        assert_eq!(part2(1), 2);
+        assert_eq!(part2(25), 26);
+        assert_eq!(part2(800), 806);
+    }
+
+    #[test]
+    fn other_rules() {
+        let mut counts = Fill::new(0, |neighbours: &[&usize]| neighbours.len());
+
+        assert_eq!(counts.by_ref().take(4).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+        assert_eq!(counts.get(3), Some(&2));
+        assert_eq!(counts.get(5), None);
     }
 }
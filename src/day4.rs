@@ -1,4 +1,7 @@
+use solution::Solution;
+
 use std::collections::HashSet;
+use std::error::Error;
 
 fn is_valid_passphrase(phrase: &str) -> bool {
     let mut set = HashSet::new();
@@ -38,6 +41,28 @@ pub fn part2(input: &str) -> u32 {
         .count() as u32
 }
 
+pub struct Day4;
+
+impl Solution for Day4 {
+    const DAY: u32 = 4;
+
+    type Input = String;
+    type Output1 = u32;
+    type Output2 = u32;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(input.trim().to_owned())
+    }
+
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input)
+    }
+
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
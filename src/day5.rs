@@ -1,3 +1,7 @@
+use solution::Solution;
+
+use std::error::Error;
+
 pub fn part1(input: &str) -> u32 {
     let mut jump_list = input
         .split_whitespace()
@@ -9,7 +13,7 @@ pub fn part1(input: &str) -> u32 {
         let offset = jump_list[current_index];
 
         let next_index = if offset.is_negative() {
-            current_index.checked_sub(offset.abs() as usize)
+            current_index.checked_sub(offset.unsigned_abs() as usize)
         } else {
             current_index.checked_add(offset as usize)
         };
@@ -37,7 +41,7 @@ pub fn part2(input: &str) -> u32 {
         let offset = jump_list[current_index];
 
         let next_index = if offset.is_negative() {
-            current_index.checked_sub(offset.abs() as usize)
+            current_index.checked_sub(offset.unsigned_abs() as usize)
         } else {
             current_index.checked_add(offset as usize)
         };
@@ -59,6 +63,28 @@ pub fn part2(input: &str) -> u32 {
     unreachable!()
 }
 
+pub struct Day5;
+
+impl Solution for Day5 {
+    const DAY: u32 = 5;
+
+    type Input = String;
+    type Output1 = u32;
+    type Output2 = u32;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(input.trim().to_owned())
+    }
+
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input)
+    }
+
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,5 +1,8 @@
+use solution::Solution;
+
 use std::collections::HashSet;
 use std::collections::HashMap;
+use std::error::Error;
 
 pub fn part1(input: &str) -> u32 {
     let mut input = input
@@ -63,6 +66,28 @@ fn find_max(memory: &[usize]) -> (usize, usize) {
     (k, v)
 }
 
+pub struct Day6;
+
+impl Solution for Day6 {
+    const DAY: u32 = 6;
+
+    type Input = String;
+    type Output1 = u32;
+    type Output2 = u32;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(input.trim().to_owned())
+    }
+
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input)
+    }
+
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
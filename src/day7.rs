@@ -1,40 +1,49 @@
 use std::collections::{HashMap, HashSet};
-use day7_parser;
+use std::error::Error;
+use day7_parser::{self, ParseError};
+use solution::Solution;
 
 use petgraph::Graph;
 use petgraph::graph::NodeIndex;
 
-#[derive(Debug, Eq, PartialEq)]
-struct Node<'a> {
-    name: &'a str,
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Node {
+    name: String,
     weight: u32,
     total_weight: u32,
-    children: Option<Vec<&'a str>>,
+    children: Option<Vec<String>>,
 }
 
-impl<'a> Node<'a> {
-    fn new(name: &'a str, weight: u32, children: Vec<&'a str>) -> Node<'a> {
+impl Node {
+    fn new(name: &str, weight: u32, children: Vec<&str>) -> Node {
         Node {
-            name,
+            name: name.to_owned(),
             weight,
             total_weight: weight,
-            children: Some(children),
+            children: Some(children.into_iter().map(str::to_owned).collect()),
         }
     }
 }
 
-pub fn part1(input: &str) -> &str {
-    let input = day7_parser::parse(input, Node::new);
+pub type Tower = HashMap<String, Node>;
 
-    find_root(&input)
+fn parse_input(input: &str) -> Result<Tower, ParseError> {
+    Ok(day7_parser::parse(input, Node::new)?
+        .into_iter()
+        .map(|(name, node)| (name.to_owned(), node))
+        .collect())
 }
 
-pub fn part2(input: &str) -> i32 {
-    let mut input = day7_parser::parse(input, Node::new);
+pub fn part1(input: &Tower) -> &str {
+    find_root(input)
+}
+
+pub fn part2(input: &Tower) -> i32 {
+    let mut input = input.clone();
 
-    let root = find_root(&input);
+    let root = find_root(&input).to_owned();
 
-    let (mut graph, root_index) = build_graph(&mut input, root);
+    let (mut graph, root_index) = build_graph(&mut input, &root);
 
     update_weight(&mut graph, root_index);
 
@@ -43,12 +52,12 @@ pub fn part2(input: &str) -> i32 {
     graph[node].weight as i32 - diff
 }
 
-fn find_root<'a, 'b>(nodes: &'a HashMap<&'b str, Node<'b>>) -> &'b str {
-    let mut names = nodes.keys().collect::<HashSet<_>>();
+fn find_root(nodes: &Tower) -> &str {
+    let mut names = nodes.keys().map(String::as_str).collect::<HashSet<_>>();
 
     for (_, node) in nodes.iter() {
         for child in node.children.as_ref().unwrap() {
-            names.remove(child);
+            names.remove(child.as_str());
         }
     }
 
@@ -57,41 +66,36 @@ fn find_root<'a, 'b>(nodes: &'a HashMap<&'b str, Node<'b>>) -> &'b str {
     names.into_iter().next().unwrap()
 }
 
-fn build_graph<'a, 'b>(
-    input: &'b mut HashMap<&'a str, Node<'a>>,
-    root: &'a str,
-) -> (Graph<Node<'a>, ()>, NodeIndex) {
+fn build_graph(input: &mut Tower, root: &str) -> (Graph<Node, ()>, NodeIndex) {
     let mut root_node = input.remove(root).expect("root not found");
 
     let mut graph = Graph::with_capacity(input.len() + 1, input.len());
-    let mut nodes = HashMap::new();
 
     let children = root_node.children.take().expect("no children found");
 
-    nodes.insert(root, graph.add_node(root_node));
+    let root_index = graph.add_node(root_node);
 
-    add_children(&mut graph, input, &mut nodes, root, children);
+    add_children(&mut graph, input, root_index, children);
 
-    (graph, nodes[root])
+    (graph, root_index)
 }
 
-fn add_children<'a, 'b>(
-    graph: &'b mut Graph<Node<'a>, ()>,
-    input: &'b mut HashMap<&'a str, Node<'a>>,
-    nodes: &'b mut HashMap<&'a str, NodeIndex>,
-    root: &'a str,
-    children: Vec<&'a str>,
+fn add_children(
+    graph: &mut Graph<Node, ()>,
+    input: &mut Tower,
+    parent: NodeIndex,
+    children: Vec<String>,
 ) {
     for child in children {
-        let mut child_node = input.remove(child).expect("root not found");
+        let mut child_node = input.remove(&child).expect("child not found");
 
         let grand_children = child_node.children.take().expect("no children found");
 
-        nodes.insert(child, graph.add_node(child_node));
+        let child_index = graph.add_node(child_node);
 
-        graph.add_edge(nodes[root], nodes[child], ());
+        graph.add_edge(parent, child_index, ());
 
-        add_children(graph, input, nodes, child, grand_children);
+        add_children(graph, input, child_index, grand_children);
     }
 }
 
@@ -139,3 +143,25 @@ fn find_wrong_weight(graph: &Graph<Node, ()>, index: NodeIndex) -> Option<(i32,
         _ => unreachable!(),
     }
 }
+
+pub struct Day7;
+
+impl Solution for Day7 {
+    const DAY: u32 = 7;
+
+    type Input = Tower;
+    type Output1 = String;
+    type Output2 = i32;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(parse_input(input.trim())?)
+    }
+
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input).to_owned()
+    }
+
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
+    }
+}
@@ -1,25 +1,27 @@
-use day8_parser::{self, Action, Operator};
+use day8_parser::{self, Action, Operator, ParseError};
+use solution::Solution;
 
 use std::collections::HashMap;
+use std::error::Error;
 
-struct CPU<'a> {
+struct Cpu<'a> {
     registers: HashMap<&'a str, i32>,
 }
 
-impl<'a> CPU<'a> {
-    fn new() -> CPU<'a> {
-        CPU {
+impl<'a> Cpu<'a> {
+    fn new() -> Cpu<'a> {
+        Cpu {
             registers: HashMap::new(),
         }
     }
 
-    fn run(&mut self, instruction: &Instruction<'a>) -> Option<i32> {
-        let (other_register, operator, value) = instruction.condition;
+    fn run(&mut self, instruction: &'a Instruction) -> Option<i32> {
+        let (ref other_register, operator, value) = instruction.condition;
 
-        let &other_value = self.registers.get(other_register).unwrap_or(&0);
+        let &other_value = self.registers.get(other_register.as_str()).unwrap_or(&0);
 
-        if CPU::is_valid(other_value, operator, value) {
-            let register = self.registers.entry(instruction.register);
+        if Cpu::is_valid(other_value, operator, value) {
+            let register = self.registers.entry(&instruction.register);
             let (action, value) = instruction.action;
 
             let register_value = register.or_insert(0);
@@ -49,35 +51,37 @@ impl<'a> CPU<'a> {
 }
 
 #[derive(Debug)]
-struct Instruction<'a> {
-    register: &'a str,
+pub struct Instruction {
+    register: String,
     action: (Action, i32),
-    condition: (&'a str, Operator, i32),
+    condition: (String, Operator, i32),
 }
 
-impl<'a> Instruction<'a> {
+impl Instruction {
     fn new(
-        reg: &'a str,
+        reg: &str,
         act: Action,
         val: i32,
-        oth_reg: &'a str,
+        oth_reg: &str,
         op: Operator,
         oth_val: i32,
-    ) -> Instruction<'a> {
+    ) -> Instruction {
         Instruction {
-            register: reg,
+            register: reg.to_owned(),
             action: (act, val),
-            condition: (oth_reg, op, oth_val),
+            condition: (oth_reg.to_owned(), op, oth_val),
         }
     }
 }
 
-pub fn part1(input: &str) -> i32 {
-    let input = day8_parser::parse(input, Instruction::new);
+fn parse_input(input: &str) -> Result<Vec<Instruction>, ParseError> {
+    day8_parser::parse(input, Instruction::new)
+}
 
-    let mut cpu = CPU::new();
+pub fn part1(input: &[Instruction]) -> i32 {
+    let mut cpu = Cpu::new();
 
-    for instruction in &input {
+    for instruction in input {
         cpu.run(instruction);
     }
 
@@ -88,10 +92,8 @@ pub fn part1(input: &str) -> i32 {
         .expect("no maximum found")
 }
 
-pub fn part2(input: &str) -> i32 {
-    let input = day8_parser::parse(input, Instruction::new);
-
-    let mut cpu = CPU::new();
+pub fn part2(input: &[Instruction]) -> i32 {
+    let mut cpu = Cpu::new();
 
     input
         .iter()
@@ -99,3 +101,25 @@ pub fn part2(input: &str) -> i32 {
         .max()
         .expect("unable to find global maximum")
 }
+
+pub struct Day8;
+
+impl Solution for Day8 {
+    const DAY: u32 = 8;
+
+    type Input = Vec<Instruction>;
+    type Output1 = i32;
+    type Output2 = i32;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(parse_input(input.trim())?)
+    }
+
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input)
+    }
+
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
+    }
+}
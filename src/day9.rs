@@ -1,14 +1,14 @@
 use day9_parser::{parse, Content, Garbage, Group};
+use solution::Solution;
 
-pub fn part1(input: &str) -> u32 {
-    let content = parse(input).expect("Failed to parse input");
+use std::error::Error;
 
-    group_score(&content, 0)
+pub fn part1(input: &Content) -> u32 {
+    group_score(input, 0)
 }
-pub fn part2(input: &str) -> u32 {
-    let content = parse(input).expect("Failed to parse input");
 
-    garbage_length(&content)
+pub fn part2(input: &Content) -> u32 {
+    garbage_length(input)
 }
 
 fn group_score(content: &Content, depth: u32) -> u32 {
@@ -25,11 +25,33 @@ fn group_score(content: &Content, depth: u32) -> u32 {
 
 fn garbage_length(content: &Content) -> u32 {
     match *content {
-        Group(ref sub_content) => sub_content.iter().map(|c| garbage_length(c)).sum(),
+        Group(ref sub_content) => sub_content.iter().map(garbage_length).sum(),
         Garbage(len) => len,
     }
 }
 
+pub struct Day9;
+
+impl Solution for Day9 {
+    const DAY: u32 = 9;
+
+    type Input = Content;
+    type Output1 = u32;
+    type Output2 = u32;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        Ok(parse(input.trim())?)
+    }
+
+    fn part1(input: &Self::Input) -> Self::Output1 {
+        part1(input)
+    }
+
+    fn part2(input: &Self::Input) -> Self::Output2 {
+        part2(input)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -37,49 +59,48 @@ mod tests {
     #[test]
     /// {}, score of 1.
     fn part1_sample1() {
-        assert_eq!(part1("{}"), 1);
+        assert_eq!(part1(&parse("{}").unwrap()), 1);
     }
 
     #[test]
     /// {{{}}}, score of 1 + 2 + 3 = 6.
     fn part1_sample2() {
-        assert_eq!(part1("{{{}}}"), 6);
+        assert_eq!(part1(&parse("{{{}}}").unwrap()), 6);
     }
 
     #[test]
     /// {{},{}}, score of 1 + 2 + 2 = 5.
     fn part1_sample3() {
-        assert_eq!(part1("{{},{}}"), 5);
+        assert_eq!(part1(&parse("{{},{}}").unwrap()), 5);
     }
 
     #[test]
     /// {{{},{},{{}}}}, score of 1 + 2 + 3 + 3 + 3 + 4 = 16.
     fn part1_sample4() {
-        assert_eq!(part1("{{{},{},{{}}}}"), 16);
+        assert_eq!(part1(&parse("{{{},{},{{}}}}").unwrap()), 16);
     }
 
     #[test]
     /// {<a>,<a>,<a>,<a>}, score of 1.
     fn part1_sample5() {
-        assert_eq!(part1("{<a>,<a>,<a>,<a>}"), 1);
+        assert_eq!(part1(&parse("{<a>,<a>,<a>,<a>}").unwrap()), 1);
     }
 
     #[test]
     /// {{<ab>},{<ab>},{<ab>},{<ab>}}, score of 1 + 2 + 2 + 2 + 2 = 9.
     fn part1_sample6() {
-        assert_eq!(part1("{{<ab>},{<ab>},{<ab>},{<ab>}}"), 9);
+        assert_eq!(part1(&parse("{{<ab>},{<ab>},{<ab>},{<ab>}}").unwrap()), 9);
     }
 
     #[test]
     /// {{<!!>},{<!!>},{<!!>},{<!!>}}, score of 1 + 2 + 2 + 2 + 2 = 9.
     fn part1_sample7() {
-        assert_eq!(part1("{{<!!>},{<!!>},{<!!>},{<!!>}}"), 9);
+        assert_eq!(part1(&parse("{{<!!>},{<!!>},{<!!>},{<!!>}}").unwrap()), 9);
     }
 
     #[test]
     /// {{<a!>},{<a!>},{<a!>},{<ab>}}, score of 1 + 2 = 3.
     fn part1_sample8() {
-        assert_eq!(part1("{{<a!>},{<a!>},{<a!>},{<ab>}}"), 3);
+        assert_eq!(part1(&parse("{{<a!>},{<a!>},{<a!>},{<ab>}}").unwrap()), 3);
     }
-
 }
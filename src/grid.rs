@@ -0,0 +1,436 @@
+//! An unbounded 2D grid, for the walkers of days 3, 22 and 25.
+//!
+//! Cells are stored in chunks, square unless the grid only uses one row, found
+//! by their position in a map so that cells far apart only cost a chunk each.
+//! Reading a cell never allocates: cells never written hold the default value
+//! of the grid.
+
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+
+/// `(x, y)`, `x` growing rightwards and `y` downwards, as the rows of a map
+/// are read. The top of `Bounds` is so its smallest `y`.
+pub type Point = (i64, i64);
+
+/// log2 of the number of cells in a chunk.
+const SHIFT: u32 = 10;
+const CELLS: usize = 1 << SHIFT;
+
+/// The width and height of chunks, as log2.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+struct Shape {
+    width: u32,
+    height: u32,
+}
+
+impl Shape {
+    const SQUARE: Shape = Shape {
+        width: SHIFT / 2,
+        height: SHIFT / 2,
+    };
+
+    const ROW: Shape = Shape {
+        width: SHIFT,
+        height: 0,
+    };
+
+    /// The chunk of a point, and the offset of the point in it.
+    fn locate(self, (x, y): Point) -> (Point, usize) {
+        let column = x & ((1 << self.width) - 1);
+        let row = y & ((1 << self.height) - 1);
+
+        (
+            (x >> self.width, y >> self.height),
+            ((row << self.width) | column) as usize,
+        )
+    }
+
+    /// The point at `offset` in `chunk`.
+    fn point(self, (cx, cy): Point, offset: usize) -> Point {
+        let offset = offset as i64;
+
+        (
+            (cx << self.width) + (offset & ((1 << self.width) - 1)),
+            (cy << self.height) + (offset >> self.width),
+        )
+    }
+}
+
+/// Hashes chunk positions by multiplying each coordinate in: enough to spread
+/// the chunks of a walk, and much cheaper than the default hasher, which every
+/// step would pay for.
+#[derive(Debug, Default, Clone, Copy)]
+struct ChunkHasher(u64);
+
+impl Hasher for ChunkHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_u64(u64::from(byte));
+        }
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = (self.0.rotate_left(5) ^ n).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+
+    fn write_i64(&mut self, n: i64) {
+        self.write_u64(n as u64);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Chunk<T> {
+    cells: Vec<T>,
+    /// A bit per cell, set once the cell is written.
+    occupied: Vec<u64>,
+}
+
+impl<T: Clone> Chunk<T> {
+    fn new(default: &T) -> Chunk<T> {
+        Chunk {
+            cells: vec![default.clone(); CELLS],
+            occupied: vec![0; CELLS / 64],
+        }
+    }
+
+    fn is_occupied(&self, offset: usize) -> bool {
+        self.occupied[offset / 64] & 1 << (offset % 64) != 0
+    }
+}
+
+/// The smallest rectangle holding some cells, all sides included.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub struct Bounds {
+    pub left: i64,
+    pub top: i64,
+    pub right: i64,
+    pub bottom: i64,
+}
+
+impl Bounds {
+    fn point((x, y): Point) -> Bounds {
+        Bounds {
+            left: x,
+            top: y,
+            right: x,
+            bottom: y,
+        }
+    }
+
+    pub fn width(&self) -> u64 {
+        (self.right - self.left + 1) as u64
+    }
+
+    pub fn height(&self) -> u64 {
+        (self.bottom - self.top + 1) as u64
+    }
+
+    pub fn contains(&self, (x, y): Point) -> bool {
+        x >= self.left && x <= self.right && y >= self.top && y <= self.bottom
+    }
+
+    fn extend(&mut self, (x, y): Point) {
+        self.left = self.left.min(x);
+        self.top = self.top.min(y);
+        self.right = self.right.max(x);
+        self.bottom = self.bottom.max(y);
+    }
+}
+
+/// A cell whose value differs between two grids.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Change<T> {
+    pub point: Point,
+    pub before: T,
+    pub after: T,
+}
+
+/// A grid without edges.
+///
+/// A cell is occupied once written, even with the default value, like a key
+/// inserted in a map.
+#[derive(Debug, Clone)]
+pub struct InfiniteGrid<T> {
+    default: T,
+    shape: Shape,
+    chunks: HashMap<Point, Chunk<T>, BuildHasherDefault<ChunkHasher>>,
+    len: usize,
+    bounds: Option<Bounds>,
+}
+
+impl<T: Clone + Default> Default for InfiniteGrid<T> {
+    fn default() -> InfiniteGrid<T> {
+        InfiniteGrid::new(T::default())
+    }
+}
+
+impl<T: Clone> InfiniteGrid<T> {
+    /// An empty grid, every cell holding `default`.
+    pub fn new(default: T) -> InfiniteGrid<T> {
+        InfiniteGrid::with_shape(default, Shape::SQUARE)
+    }
+
+    /// An empty grid for cells on a single row, its chunks being one row
+    /// high. Other rows can still be used, a chunk each.
+    pub fn row(default: T) -> InfiniteGrid<T> {
+        InfiniteGrid::with_shape(default, Shape::ROW)
+    }
+
+    fn with_shape(default: T, shape: Shape) -> InfiniteGrid<T> {
+        InfiniteGrid {
+            default,
+            shape,
+            chunks: HashMap::default(),
+            len: 0,
+            bounds: None,
+        }
+    }
+
+    /// Number of occupied cells.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The smallest rectangle holding every occupied cell.
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.bounds
+    }
+
+    pub fn get(&self, point: Point) -> &T {
+        let (chunk, offset) = self.shape.locate(point);
+
+        self.chunks
+            .get(&chunk)
+            .map_or(&self.default, |chunk| &chunk.cells[offset])
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        let (chunk, offset) = self.shape.locate(point);
+
+        self.chunks
+            .get(&chunk)
+            .is_some_and(|chunk| chunk.is_occupied(offset))
+    }
+
+    /// The cell at `point`, which becomes occupied.
+    pub fn get_mut(&mut self, point: Point) -> &mut T {
+        let (chunk, offset) = self.shape.locate(point);
+
+        let default = &self.default;
+        let chunk = self
+            .chunks
+            .entry(chunk)
+            .or_insert_with(|| Chunk::new(default));
+        let (word, bit) = (offset / 64, 1 << (offset % 64));
+
+        if chunk.occupied[word] & bit == 0 {
+            chunk.occupied[word] |= bit;
+            self.len += 1;
+
+            match self.bounds {
+                Some(ref mut bounds) => bounds.extend(point),
+                None => self.bounds = Some(Bounds::point(point)),
+            }
+        }
+
+        &mut chunk.cells[offset]
+    }
+
+    pub fn set(&mut self, point: Point, value: T) {
+        *self.get_mut(point) = value;
+    }
+
+    /// The occupied cells, chunk by chunk.
+    pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> {
+        let shape = self.shape;
+
+        self.chunks.iter().flat_map(move |(&position, chunk)| {
+            (0..CELLS)
+                .filter(move |&offset| chunk.is_occupied(offset))
+                .map(move |offset| (shape.point(position, offset), &chunk.cells[offset]))
+        })
+    }
+
+    /// A copy of the grid as it is now, to `diff` with later.
+    pub fn snapshot(&self) -> InfiniteGrid<T> {
+        self.clone()
+    }
+}
+
+impl<T: Clone + PartialEq> InfiniteGrid<T> {
+    /// The cells that differ from `before`, among those occupied in either
+    /// grid.
+    pub fn diff(&self, before: &InfiniteGrid<T>) -> Vec<Change<T>> {
+        let changed = |point: Point| {
+            let (old, new) = (before.get(point), self.get(point));
+
+            if old != new {
+                Some(Change {
+                    point,
+                    before: old.clone(),
+                    after: new.clone(),
+                })
+            } else {
+                None
+            }
+        };
+
+        let mut changes = self
+            .iter()
+            .filter_map(|(point, _)| changed(point))
+            .collect::<Vec<_>>();
+
+        changes.extend(
+            before
+                .iter()
+                .filter(|&(point, _)| !self.contains(point))
+                .filter_map(|(point, _)| changed(point)),
+        );
+
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_everywhere() {
+        let mut grid = InfiniteGrid::new('.');
+
+        assert_eq!(*grid.get((5, -3)), '.');
+        assert!(grid.is_empty());
+
+        let points = [
+            (0, 0),
+            (-1, -1),
+            (1000, 3),
+            (-250, 777),
+            (31, 32),
+            (-32, -33),
+        ];
+
+        for (i, &point) in points.iter().enumerate() {
+            grid.set(point, (b'a' + i as u8) as char);
+        }
+
+        for (i, &point) in points.iter().enumerate() {
+            assert_eq!(*grid.get(point), (b'a' + i as u8) as char);
+            assert!(grid.contains(point));
+        }
+
+        assert!(!grid.contains((1, 0)));
+        assert_eq!(grid.len(), points.len());
+        assert_eq!(
+            grid.bounds(),
+            Some(Bounds {
+                left: -250,
+                top: -33,
+                right: 1000,
+                bottom: 777,
+            })
+        );
+
+        let mut cells = grid
+            .iter()
+            .map(|(point, &c)| (point, c))
+            .collect::<Vec<_>>();
+        cells.sort();
+        assert_eq!(cells[0], ((-250, 777), 'd'));
+        assert_eq!(cells.len(), points.len());
+    }
+
+    #[test]
+    fn far_apart() {
+        let mut grid = InfiniteGrid::new(0);
+        let far = 1_000_000_000;
+
+        grid.set((0, 0), 1);
+        grid.set((far, far), 2);
+        grid.set((-far, far), 3);
+
+        assert_eq!(*grid.get((far, far)), 2);
+        assert_eq!(*grid.get((-far, far)), 3);
+        assert_eq!(grid.chunks.len(), 3);
+        assert_eq!(grid.bounds().unwrap().width(), 2 * far as u64 + 1);
+    }
+
+    #[test]
+    fn rows() {
+        let mut grid = InfiniteGrid::row(0);
+        let points = [(0, 0), (-1, 0), (1023, 0), (1024, 0), (-5000, 0), (3, 2)];
+
+        for (i, &point) in points.iter().enumerate() {
+            grid.set(point, i + 1);
+        }
+
+        for (i, &point) in points.iter().enumerate() {
+            assert_eq!(*grid.get(point), i + 1);
+        }
+
+        let mut cells = grid
+            .iter()
+            .map(|(point, &v)| (point, v))
+            .collect::<Vec<_>>();
+        cells.sort();
+        assert_eq!(cells.len(), points.len());
+        assert_eq!(cells[0], ((-5000, 0), 5));
+        assert_eq!(grid.bounds().unwrap().height(), 3);
+    }
+
+    #[test]
+    fn default_values_are_occupied() {
+        let mut grid = InfiniteGrid::<u8>::default();
+
+        *grid.get_mut((3, 4)) += 0;
+
+        assert!(grid.contains((3, 4)));
+        assert_eq!(grid.iter().collect::<Vec<_>>(), vec![((3, 4), &0)]);
+        assert_eq!(grid.bounds().unwrap().width(), 1);
+    }
+
+    #[test]
+    fn snapshots() {
+        let mut grid = InfiniteGrid::new(0);
+        grid.set((0, 0), 1);
+        grid.set((-40, 2), 2);
+
+        let before = grid.snapshot();
+
+        grid.set((0, 0), 3);
+        grid.set((-40, 2), 2);
+        grid.set((90, 90), 4);
+        grid.set((7, 7), 0);
+
+        let mut changes = grid.diff(&before);
+        changes.sort_by_key(|change| change.point);
+
+        assert_eq!(
+            changes,
+            vec![
+                Change {
+                    point: (0, 0),
+                    before: 1,
+                    after: 3
+                },
+                Change {
+                    point: (90, 90),
+                    before: 0,
+                    after: 4
+                },
+            ]
+        );
+
+        assert_eq!(before.diff(&grid).len(), 2);
+        assert_eq!(grid.diff(&grid.snapshot()), vec![]);
+    }
+}
@@ -0,0 +1,455 @@
+//! Geometry of a grid of flat-topped hexagons, as on day 11.
+//!
+//! A `Hex` is stored in axial coordinates: `x` grows to the south east and `y`
+//! to the south, the third cube coordinate being `z = -x - y`.
+//!
+//! ```text
+//!   \ n  /
+//! nw +--+ ne
+//!   /    \
+//! -+      +-
+//!   \    /
+//! sw +--+ se
+//!   / s  \
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::iter;
+use std::ops::{Add, Mul, Sub};
+use std::str::FromStr;
+
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub enum Direction {
+    North,
+    NorthEast,
+    SouthEast,
+    South,
+    SouthWest,
+    NorthWest,
+}
+
+impl Direction {
+    /// Clockwise, from the north.
+    pub const ALL: [Direction; 6] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::NorthWest,
+    ];
+
+    /// The hex next to the origin in that direction.
+    pub fn offset(self) -> Hex {
+        use self::Direction::*;
+
+        match self {
+            North => Hex::new(0, -1),
+            NorthEast => Hex::new(1, -1),
+            SouthEast => Hex::new(1, 0),
+            South => Hex::new(0, 1),
+            SouthWest => Hex::new(-1, 1),
+            NorthWest => Hex::new(-1, 0),
+        }
+    }
+
+    fn index(self) -> usize {
+        Direction::ALL
+            .iter()
+            .position(|&direction| direction == self)
+            .expect("every direction is listed")
+    }
+
+    /// The direction `sixths` sixths of a turn clockwise from this one.
+    pub fn turn(self, sixths: i32) -> Direction {
+        Direction::ALL[(self.index() as i32 + sixths).rem_euclid(6) as usize]
+    }
+
+    pub fn opposite(self) -> Direction {
+        self.turn(3)
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Direction::*;
+
+        f.write_str(match *self {
+            North => "n",
+            NorthEast => "ne",
+            SouthEast => "se",
+            South => "s",
+            SouthWest => "sw",
+            NorthWest => "nw",
+        })
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ParseDirectionError(String);
+
+impl fmt::Display for ParseDirectionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid direction {:?}, expected n, ne, se, s, sw or nw",
+            self.0
+        )
+    }
+}
+
+impl Error for ParseDirectionError {}
+
+impl FromStr for Direction {
+    type Err = ParseDirectionError;
+
+    fn from_str(s: &str) -> Result<Self, ParseDirectionError> {
+        use self::Direction::*;
+
+        match s {
+            "n" => Ok(North),
+            "ne" => Ok(NorthEast),
+            "se" => Ok(SouthEast),
+            "s" => Ok(South),
+            "sw" => Ok(SouthWest),
+            "nw" => Ok(NorthWest),
+            _ => Err(ParseDirectionError(s.to_owned())),
+        }
+    }
+}
+
+/// Parses a comma separated list of directions.
+pub fn parse_path(s: &str) -> Result<Vec<Direction>, ParseDirectionError> {
+    s.split(',')
+        .map(|direction| direction.trim().parse())
+        .collect()
+}
+
+/// A hex, in axial coordinates.
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone, Default)]
+pub struct Hex {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Hex {
+    pub const ORIGIN: Hex = Hex { x: 0, y: 0 };
+
+    pub fn new(x: i32, y: i32) -> Hex {
+        Hex { x, y }
+    }
+
+    pub fn z(self) -> i32 {
+        -self.x - self.y
+    }
+
+    pub fn step(self, direction: Direction) -> Hex {
+        self + direction.offset()
+    }
+
+    /// Number of steps from the origin.
+    pub fn length(self) -> u32 {
+        self.x.abs().max(self.y.abs()).max(self.z().abs()) as u32
+    }
+
+    pub fn distance(self, other: Hex) -> u32 {
+        (other - self).length()
+    }
+
+    /// The six hexes around this one, clockwise from the north.
+    pub fn neighbours(self) -> impl Iterator<Item = Hex> {
+        Direction::ALL
+            .iter()
+            .map(move |&direction| self.step(direction))
+    }
+
+    /// The `6 * radius` hexes at `radius` steps, clockwise from the north, or
+    /// only this one for a radius of 0.
+    pub fn ring(self, radius: u32) -> impl Iterator<Item = Hex> {
+        let start = self + Direction::North.offset() * radius as i32;
+
+        let sides = if radius == 0 { 1 } else { 6 };
+        let length = radius.max(1);
+
+        (0..sides)
+            .flat_map(move |side| iter::repeat_n(side, length as usize))
+            .scan(start, |hex, side| {
+                let current = *hex;
+
+                // Each side starts at a corner, going a third of a turn from
+                // the direction of that corner.
+                *hex = hex.step(Direction::North.turn(side + 2));
+                Some(current)
+            })
+    }
+
+    /// This hex, then the rings around it up to `radius`.
+    pub fn spiral(self, radius: u32) -> impl Iterator<Item = Hex> {
+        (0..=radius).flat_map(move |r| self.ring(r))
+    }
+
+    /// The hexes a straight line from this one to `other` goes through, both
+    /// ends included.
+    pub fn line_to(self, other: Hex) -> Vec<Hex> {
+        let n = self.distance(other);
+
+        if n == 0 {
+            return vec![self];
+        }
+
+        // Nudged so that points on an edge always round to the same side.
+        let (from, to) = (Cube::from(self), Cube::from(other));
+        let lerp = |a: i32, b: i32, t: f64, nudge: f64| f64::from(a) + f64::from(b - a) * t + nudge;
+
+        (0..=n)
+            .map(|i| {
+                let t = f64::from(i) / f64::from(n);
+
+                Hex::from(Cube::round(
+                    lerp(from.x, to.x, t, 1e-6),
+                    lerp(from.y, to.y, t, 2e-6),
+                    lerp(from.z, to.z, t, -3e-6),
+                ))
+            })
+            .collect()
+    }
+
+    /// A shortest path to `other`, the moves in each direction grouped.
+    pub fn path_to(self, other: Hex) -> Vec<Direction> {
+        let mut path = Vec::with_capacity(self.distance(other) as usize);
+        let mut hex = self;
+
+        while hex != other {
+            let distance = hex.distance(other);
+            let direction = *Direction::ALL
+                .iter()
+                .find(|&&direction| hex.step(direction).distance(other) < distance)
+                .expect("a neighbour is always closer");
+
+            hex = hex.step(direction);
+            path.push(direction);
+        }
+
+        path
+    }
+
+    pub fn to_offset(self, layout: Layout) -> Offset {
+        Offset {
+            column: self.x,
+            row: self.y + layout.shift(self.x),
+        }
+    }
+
+    pub fn from_offset(offset: Offset, layout: Layout) -> Hex {
+        Hex::new(offset.column, offset.row - layout.shift(offset.column))
+    }
+}
+
+impl Add for Hex {
+    type Output = Hex;
+
+    fn add(self, other: Hex) -> Hex {
+        Hex::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Hex {
+    type Output = Hex;
+
+    fn sub(self, other: Hex) -> Hex {
+        Hex::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Mul<i32> for Hex {
+    type Output = Hex;
+
+    fn mul(self, factor: i32) -> Hex {
+        Hex::new(self.x * factor, self.y * factor)
+    }
+}
+
+/// Where a path from the origin ends.
+pub fn walk(path: &[Direction]) -> Hex {
+    path.iter()
+        .fold(Hex::ORIGIN, |hex, &direction| hex.step(direction))
+}
+
+/// The shortest list of moves going to the same hex as `path`.
+pub fn simplify(path: &[Direction]) -> Vec<Direction> {
+    Hex::ORIGIN.path_to(walk(path))
+}
+
+/// A hex in cube coordinates, where `x + y + z == 0`.
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub struct Cube {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Cube {
+    /// The cube nearest to a point, fixing the coordinate that moved the most
+    /// so the three still add up to 0.
+    fn round(x: f64, y: f64, z: f64) -> Cube {
+        let (mut rx, mut ry, mut rz) = (x.round(), y.round(), z.round());
+        let (dx, dy, dz) = ((rx - x).abs(), (ry - y).abs(), (rz - z).abs());
+
+        if dx > dy && dx > dz {
+            rx = -ry - rz;
+        } else if dy > dz {
+            ry = -rx - rz;
+        } else {
+            rz = -rx - ry;
+        }
+
+        Cube {
+            x: rx as i32,
+            y: ry as i32,
+            z: rz as i32,
+        }
+    }
+}
+
+impl From<Hex> for Cube {
+    fn from(hex: Hex) -> Cube {
+        Cube {
+            x: hex.x,
+            y: hex.y,
+            z: hex.z(),
+        }
+    }
+}
+
+impl From<Cube> for Hex {
+    fn from(cube: Cube) -> Hex {
+        Hex::new(cube.x, cube.y)
+    }
+}
+
+/// Which columns are pushed down by half a hex in offset coordinates.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Layout {
+    EvenQ,
+    OddQ,
+}
+
+impl Layout {
+    /// How many rows the axial `y` of a column is behind its offset row.
+    fn shift(self, column: i32) -> i32 {
+        match self {
+            Layout::EvenQ => (column + (column & 1)) / 2,
+            Layout::OddQ => (column - (column & 1)) / 2,
+        }
+    }
+}
+
+/// A hex in offset coordinates, as stored in a rectangular array.
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub struct Offset {
+    pub column: i32,
+    pub row: i32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use self::Direction::*;
+
+    #[test]
+    fn directions() {
+        assert_eq!("nw".parse(), Ok(NorthWest));
+        assert_eq!(
+            "up".parse::<Direction>(),
+            Err(ParseDirectionError("up".to_owned()))
+        );
+        assert!(parse_path("n,ne,x").is_err());
+
+        assert_eq!(North.opposite(), South);
+        assert_eq!(NorthWest.turn(1), North);
+        assert_eq!(North.turn(-1), NorthWest);
+        assert!(Direction::ALL
+            .iter()
+            .all(|&d| d.offset() + d.opposite().offset() == Hex::ORIGIN));
+    }
+
+    #[test]
+    fn coordinates() {
+        let hex = Hex::new(-3, 5);
+
+        assert_eq!(Cube::from(hex), Cube { x: -3, y: 5, z: -2 });
+        assert_eq!(Hex::from(Cube::from(hex)), hex);
+
+        for &layout in &[Layout::EvenQ, Layout::OddQ] {
+            assert!(Hex::ORIGIN
+                .spiral(4)
+                .all(|hex| Hex::from_offset(hex.to_offset(layout), layout) == hex));
+        }
+
+        // Going south east from an even column stays on the row in odd-q.
+        assert_eq!(
+            Hex::new(2, 0).step(SouthEast).to_offset(Layout::OddQ),
+            Offset { column: 3, row: 1 }
+        );
+        assert_eq!(
+            Hex::new(2, 0).step(SouthEast).to_offset(Layout::EvenQ),
+            Offset { column: 3, row: 2 }
+        );
+    }
+
+    #[test]
+    fn rings() {
+        assert_eq!(Hex::ORIGIN.ring(0).collect::<Vec<_>>(), vec![Hex::ORIGIN]);
+        assert_eq!(
+            Hex::ORIGIN.ring(1).collect::<Vec<_>>(),
+            Hex::ORIGIN.neighbours().collect::<Vec<_>>()
+        );
+
+        let center = Hex::new(4, -2);
+        let ring = center.ring(3).collect::<Vec<_>>();
+
+        assert_eq!(ring.len(), 18);
+        assert!(ring.iter().all(|&hex| hex.distance(center) == 3));
+        assert_eq!(ring[0], center + North.offset() * 3);
+
+        let spiral = center.spiral(3).collect::<Vec<_>>();
+        assert_eq!(spiral.len(), 1 + 6 + 12 + 18);
+    }
+
+    #[test]
+    fn lines() {
+        let from = Hex::new(-2, 1);
+        let to = Hex::new(3, -3);
+        let line = from.line_to(to);
+
+        assert_eq!(line.len(), 6);
+        assert_eq!((line[0], line[5]), (from, to));
+        assert!(line.windows(2).all(|pair| pair[0].distance(pair[1]) == 1));
+
+        assert_eq!(
+            Hex::ORIGIN.line_to(Hex::new(0, 3)),
+            vec![Hex::ORIGIN, Hex::new(0, 1), Hex::new(0, 2), Hex::new(0, 3)]
+        );
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(
+            simplify(&parse_path("se,sw,se,sw,sw").unwrap()),
+            vec![South, South, SouthWest]
+        );
+        assert_eq!(simplify(&parse_path("ne,ne,sw,sw").unwrap()), vec![]);
+        assert_eq!(
+            simplify(&parse_path("ne,ne,s,s").unwrap()),
+            vec![SouthEast, SouthEast]
+        );
+
+        let path = parse_path("n,n,se,s,sw,nw,n,ne,ne,s").unwrap();
+        let simple = simplify(&path);
+
+        assert_eq!(walk(&simple), walk(&path));
+        assert_eq!(simple.len() as u32, walk(&path).length());
+    }
+}
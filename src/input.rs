@@ -0,0 +1,125 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Environment variable pointing to a directory of `dayN.txt` puzzle inputs.
+pub const INPUTS_DIR_VAR: &str = "AOC_INPUTS";
+
+/// Where to read a puzzle input from.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Source {
+    /// A single input file.
+    File(PathBuf),
+    /// The standard input, read to the end.
+    Stdin,
+    /// A directory containing one `dayN.txt` file per day.
+    Directory(PathBuf),
+    /// The inputs compiled from the `resources` directory.
+    Embedded,
+}
+
+impl Source {
+    /// The directory configured with `AOC_INPUTS` if any, otherwise the embedded inputs.
+    pub fn from_env() -> Source {
+        env::var_os(INPUTS_DIR_VAR)
+            .map(|dir| Source::Directory(dir.into()))
+            .unwrap_or(Source::Embedded)
+    }
+
+    /// Reads the input of the given day.
+    ///
+    /// A day missing from a `Directory` falls back to its embedded input.
+    pub fn load(&self, day: u32) -> io::Result<String> {
+        match *self {
+            Source::File(ref path) => read_file(path),
+            Source::Stdin => {
+                let mut input = String::new();
+                io::stdin().read_to_string(&mut input)?;
+
+                Ok(input)
+            }
+            Source::Directory(ref dir) => {
+                let path = dir.join(format!("day{}.txt", day));
+
+                if path.is_file() {
+                    read_file(&path)
+                } else {
+                    Source::Embedded.load(day)
+                }
+            }
+            Source::Embedded => embedded(day).map(str::to_owned).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no embedded input for day {}", day),
+                )
+            }),
+        }
+    }
+}
+
+fn read_file(path: &Path) -> io::Result<String> {
+    let mut input = String::new();
+
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut input))
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
+
+    Ok(input)
+}
+
+/// The input of the given day, as compiled from `resources/dayN.txt`.
+pub fn embedded(day: u32) -> Option<&'static str> {
+    macro_rules! embed {
+        ($day:expr, $($n:expr),*) => {
+            match $day {
+                $($n => Some(include_str!(concat!("../resources/day", $n, ".txt"))),)*
+                _ => None,
+            }
+        };
+    }
+
+    embed!(
+        day, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24,
+        25
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+    use std::io::Write;
+
+    #[test]
+    fn embedded_inputs() {
+        assert!((1..26).all(|day| embedded(day).is_some()));
+        assert_eq!(embedded(0), None);
+        assert_eq!(embedded(3), Some("361527\n"));
+    }
+
+    #[test]
+    fn directory_with_fallback() {
+        let dir = env::temp_dir().join(format!("aoc2017-inputs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        File::create(dir.join("day3.txt"))
+            .and_then(|mut file| file.write_all(b"1024\n"))
+            .unwrap();
+
+        let source = Source::Directory(dir.clone());
+
+        assert_eq!(source.load(3).unwrap(), "1024\n");
+        assert_eq!(source.load(17).unwrap(), "366\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_file() {
+        let source = Source::File("does/not/exist.txt".into());
+
+        assert_eq!(source.load(1).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
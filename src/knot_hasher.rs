@@ -1,3 +1,27 @@
+//! The knot hash of day 10, used by day 14 to build its disk.
+//!
+//! ```
+//! use aoc2017::knot_hasher::{knot_hash, Digest, KnotHashBuilder};
+//!
+//! let mut hash = KnotHashBuilder::default().build();
+//! hash.update(b"AoC ");
+//! hash.update(b"2017");
+//!
+//! let digest = hash.finalize();
+//! assert_eq!(digest, knot_hash("AoC 2017"));
+//! assert_eq!(digest.to_string(), "33efeb34ea91902bb2f59c9920caa6cd");
+//! assert_eq!("33efeb34ea91902bb2f59c9920caa6cd".parse::<Digest>(), Ok(digest));
+//! ```
+
+use rayon::prelude::*;
+use time;
+
+use std::error::Error;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::str::FromStr;
+
+/// The state of the circular list, one length at a time.
 #[derive(Debug, Eq, PartialEq)]
 pub struct KnotHasher {
     pub list: Vec<u8>,
@@ -16,62 +40,511 @@ impl KnotHasher {
 
     pub fn hash(&mut self, length: u8) {
         let length = length as usize;
-        let len = self.list.len();
 
-        let mut section = self.list
+        reverse(&mut self.list, self.pos, length);
+
+        self.pos = (self.pos + length + self.skip) % self.list.len();
+
+        self.skip += 1;
+    }
+
+    /// XORs the list in 16 blocks of consecutive elements.
+    pub fn dense_hash(&self) -> Digest {
+        dense_hash(&self.list)
+    }
+}
+
+/// Reverses the `length` elements starting at `pos`, wrapping around the end.
+///
+/// `length` is a byte, so at most 255.
+fn reverse(list: &mut [u8], pos: usize, length: usize) {
+    let len = list.len();
+
+    if length > len {
+        // Elements are read more than once: the last copy written wins.
+        let mut section = [0; 255];
+        let section = &mut section[..length];
+
+        for (i, element) in section.iter_mut().enumerate() {
+            *element = list[(pos + i) % len];
+        }
+
+        for (i, &reversed) in section.iter().rev().enumerate() {
+            list[(pos + i) % len] = reversed;
+        }
+
+        return;
+    }
+
+    if pos + length <= len {
+        list[pos..pos + length].reverse();
+        return;
+    }
+
+    let (mut i, mut j) = (pos, (pos + length - 1) % len);
+
+    for _ in 0..length / 2 {
+        list.swap(i, j);
+
+        i = if i + 1 == len { 0 } else { i + 1 };
+        j = if j == 0 { len - 1 } else { j - 1 };
+    }
+}
+
+fn dense_hash(list: &[u8]) -> Digest {
+    let block = list.len() / 16;
+    let mut digest = [0; 16];
+
+    for (xor, chunk) in digest.iter_mut().zip(list.chunks(block)) {
+        *xor = chunk.iter().fold(0, |xor, &val| xor ^ val);
+    }
+
+    Digest(digest)
+}
+
+/// The list before the first length, on the stack.
+fn initial_list() -> [u8; 256] {
+    let mut list = [0; 256];
+
+    for (i, val) in list.iter_mut().enumerate() {
+        *val = i as u8;
+    }
+
+    list
+}
+
+/// A 128 bit knot hash, printed and parsed as 32 hex digits.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Copy, Clone, Default)]
+pub struct Digest(pub [u8; 16]);
+
+impl Digest {
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+
+    /// The 128 bits, most significant first.
+    pub fn bits(&self) -> impl Iterator<Item = bool> + '_ {
+        self.0
             .iter()
-            .cloned()
-            .cycle()
-            .skip(self.pos)
-            .take(length)
-            .collect::<Vec<_>>();
-        section.reverse();
+            .flat_map(|&byte| (0..8).rev().map(move |i| byte & (1 << i) != 0))
+    }
+}
+
+impl From<Digest> for u128 {
+    fn from(digest: Digest) -> u128 {
+        u128::from_be_bytes(digest.0)
+    }
+}
 
-        for (i, &reversed) in section.iter().enumerate().take(length) {
-            self.list[(self.pos + i) % len] = reversed;
+impl From<u128> for Digest {
+    fn from(value: u128) -> Digest {
+        Digest(value.to_be_bytes())
+    }
+}
+
+impl fmt::LowerHex for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
         }
 
-        self.pos = (self.pos + length + self.skip) % len;
+        Ok(())
+    }
+}
 
-        self.skip += 1;
+impl fmt::UpperHex for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02X}", byte)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(self, f)
     }
+}
 
-    pub fn dense_hash(&self) -> Vec<u8> {
-        let mut hash = Vec::with_capacity(16);
+#[derive(Debug, Eq, PartialEq)]
+pub struct ParseDigestError(String);
 
-        for i in 0..16 {
-            let mut xor = None;
-            for j in 0..16 {
-                let val = self.list[i * 16 + j];
-                xor = if let Some(xor) = xor.take() {
-                    Some(xor ^ val)
-                } else {
-                    Some(val)
-                }
+impl fmt::Display for ParseDigestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid digest {:?}, expected 32 hex digits", self.0)
+    }
+}
+
+impl Error for ParseDigestError {}
+
+/// Parses 32 hex digits, in either case.
+impl FromStr for Digest {
+    type Err = ParseDigestError;
+
+    fn from_str(s: &str) -> Result<Self, ParseDigestError> {
+        // `from_str_radix` would also take a sign.
+        if s.len() != 32 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(ParseDigestError(s.to_owned()));
+        }
+
+        let mut digest = [0; 16];
+
+        for (i, byte) in digest.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16)
+                .map_err(|_| ParseDigestError(s.to_owned()))?;
+        }
+
+        Ok(Digest(digest))
+    }
+}
+
+/// The parameters of a knot hash, those of the puzzle by default.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct KnotHashBuilder {
+    size: u32,
+    rounds: usize,
+    suffix: Vec<u8>,
+}
+
+impl Default for KnotHashBuilder {
+    fn default() -> KnotHashBuilder {
+        KnotHashBuilder {
+            size: 256,
+            rounds: 64,
+            suffix: vec![17, 31, 73, 47, 23],
+        }
+    }
+}
+
+impl KnotHashBuilder {
+    /// Number of elements in the list.
+    ///
+    /// # Panics
+    ///
+    /// If `size` isn't a multiple of 16 between 16 and 256, as the list is
+    /// folded into 16 bytes.
+    pub fn size(mut self, size: u32) -> KnotHashBuilder {
+        assert!(
+            size > 0 && size <= 256 && size.is_multiple_of(16),
+            "knot hash size must be a multiple of 16 up to 256, got {}",
+            size
+        );
+
+        self.size = size;
+        self
+    }
+
+    /// Number of times the lengths are applied.
+    pub fn rounds(mut self, rounds: usize) -> KnotHashBuilder {
+        self.rounds = rounds;
+        self
+    }
+
+    /// Lengths appended to the key.
+    pub fn suffix(mut self, suffix: &[u8]) -> KnotHashBuilder {
+        self.suffix = suffix.to_vec();
+        self
+    }
+
+    pub fn build(&self) -> KnotHash {
+        KnotHash {
+            params: self.clone(),
+            key: Vec::new(),
+        }
+    }
+
+    /// Hashes a single key, without allocating.
+    pub fn hash(&self, key: &[u8]) -> Digest {
+        let size = self.size as usize;
+        let mut list = initial_list();
+        let (mut pos, mut skip) = (0, 0);
+
+        for _ in 0..self.rounds {
+            for &length in key.iter().chain(&self.suffix) {
+                let length = length as usize;
+
+                reverse(&mut list[..size], pos, length);
+
+                pos = (pos + length + skip) % size;
+                skip += 1;
             }
+        }
+
+        dense_hash(&list[..size])
+    }
+
+    /// Hashes every key, in parallel.
+    pub fn hash_all<K: AsRef<[u8]> + Sync>(&self, keys: &[K]) -> Vec<Digest> {
+        keys.par_iter().map(|key| self.hash(key.as_ref())).collect()
+    }
+
+    /// Same as `hash_all`, keys of the same length being hashed `LANES` at a
+    /// time, step by step.
+    pub fn hash_all_lanes<K: AsRef<[u8]> + Sync>(&self, keys: &[K]) -> Vec<Digest> {
+        let mut order = (0..keys.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| keys[i].as_ref().len());
+
+        let mut groups = Vec::new();
+
+        for same_length in order.chunk_by(|&a, &b| keys[a].as_ref().len() == keys[b].as_ref().len())
+        {
+            groups.extend(same_length.chunks(LANES));
+        }
 
-            hash.push(xor.expect("failed to compute XOR"));
+        let digests = groups
+            .par_iter()
+            .map(|group| {
+                let group = group.iter().map(|&i| keys[i].as_ref()).collect::<Vec<_>>();
+                self.hash_lanes(&group)
+            })
+            .collect::<Vec<_>>();
+
+        let mut result = vec![Digest::default(); keys.len()];
+
+        for (group, digests) in groups.iter().zip(digests) {
+            for (&i, digest) in group.iter().zip(digests) {
+                result[i] = digest;
+            }
         }
 
-        hash
+        result
     }
 
-    pub fn dense_hash_from_key(key: &str) -> Vec<u8> {
-        let mut key = key.chars().map(|c| c as u8).collect::<Vec<_>>();
-        key.push(17);
-        key.push(31);
-        key.push(73);
-        key.push(47);
-        key.push(23);
+    /// Hashes up to `LANES` keys of the same length, which share their skip
+    /// size at every step.
+    fn hash_lanes(&self, keys: &[&[u8]]) -> Vec<Digest> {
+        let size = self.size as usize;
+        let mut lists = [initial_list(); LANES];
+        let mut pos = [0; LANES];
+        let mut skip = 0;
 
-        let mut hasher = KnotHasher::new(256);
+        let key_len = keys[0].len();
+
+        for _ in 0..self.rounds {
+            for step in 0..key_len + self.suffix.len() {
+                for (lane, key) in keys.iter().enumerate() {
+                    let length = if step < key_len {
+                        key[step]
+                    } else {
+                        self.suffix[step - key_len]
+                    } as usize;
 
-        for _ in 0..64 {
-            for &length in &key {
-                hasher.hash(length)
+                    reverse(&mut lists[lane][..size], pos[lane], length);
+
+                    pos[lane] = (pos[lane] + length + skip) % size;
+                }
+
+                skip += 1;
             }
         }
 
-        hasher.dense_hash()
+        lists[..keys.len()]
+            .iter()
+            .map(|list| dense_hash(&list[..size]))
+            .collect()
+    }
+}
+
+impl BuildHasher for KnotHashBuilder {
+    type Hasher = KnotHash;
+
+    fn build_hasher(&self) -> KnotHash {
+        self.build()
+    }
+}
+
+/// Number of keys `hash_all_lanes` hashes together.
+pub const LANES: usize = 8;
+
+/// How fast a batch of keys was hashed.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Throughput {
+    pub hashes: usize,
+    pub seconds: f64,
+}
+
+impl Throughput {
+    /// Runs `batch`, counting the digests it returns.
+    pub fn measure<F: FnOnce() -> Vec<Digest>>(batch: F) -> (Vec<Digest>, Throughput) {
+        let start = time::precise_time_s();
+        let digests = batch();
+        let seconds = time::precise_time_s() - start;
+
+        let throughput = Throughput {
+            hashes: digests.len(),
+            seconds,
+        };
+
+        (digests, throughput)
+    }
+
+    pub fn hashes_per_second(&self) -> f64 {
+        self.hashes as f64 / self.seconds
+    }
+}
+
+impl fmt::Display for Throughput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} hashes in {:.3}s ({:.0} hashes/s)",
+            self.hashes,
+            self.seconds,
+            self.hashes_per_second()
+        )
+    }
+}
+
+/// A knot hash being computed, fed with `update`.
+///
+/// Every round goes over the whole key, so the key is kept until `finalize`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct KnotHash {
+    params: KnotHashBuilder,
+    key: Vec<u8>,
+}
+
+impl KnotHash {
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.key.extend_from_slice(bytes);
+    }
+
+    pub fn finalize(&self) -> Digest {
+        self.params.hash(&self.key)
+    }
+}
+
+/// The first 64 bits of the digest.
+impl Hasher for KnotHash {
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        (u128::from(self.finalize()) >> 64) as u64
+    }
+}
+
+/// The knot hash of the puzzle, each character of `key` truncated to a byte.
+pub fn knot_hash(key: &str) -> Digest {
+    let key = key.chars().map(|c| c as u8).collect::<Vec<_>>();
+
+    let mut hash = KnotHashBuilder::default().build();
+    hash.update(&key);
+    hash.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashSet;
+
+    #[test]
+    fn parse_and_format() {
+        let digest = "3EFBE78A8D82F29979031A4AA0B16A9D".parse::<Digest>().unwrap();
+
+        assert_eq!(digest, knot_hash("1,2,3"));
+        assert_eq!(
+            format!("{:X}", digest),
+            "3EFBE78A8D82F29979031A4AA0B16A9D"
+        );
+        assert_eq!(u128::from(digest), 0x3efbe78a8d82f29979031a4aa0b16a9d);
+
+        assert!("3efbe78a".parse::<Digest>().is_err());
+        assert!("3efbe78a8d82f29979031a4aa0b16a9g".parse::<Digest>().is_err());
+        assert!("+3fbe78a8d82f29979031a4aa0b16a9d".parse::<Digest>().is_err());
+    }
+
+    #[test]
+    fn bits() {
+        let digest = Digest::from(0xa0c2_0170 << 96);
+
+        assert_eq!(
+            digest.bits().take(12).collect::<Vec<_>>(),
+            [true, false, true, false, false, false, false, false, true, true, false, false]
+        );
+    }
+
+    #[test]
+    fn parameters() {
+        let default = knot_hash("AoC 2017");
+
+        let builder = KnotHashBuilder::default().rounds(1).suffix(&[]);
+        let mut hash = builder.build();
+        hash.update(b"AoC 2017");
+        assert_ne!(hash.finalize(), default);
+
+        let mut hasher = KnotHasher::new(256);
+        b"AoC 2017".iter().for_each(|&length| hasher.hash(length));
+        assert_eq!(hash.finalize(), hasher.dense_hash());
+
+        let mut small = KnotHashBuilder::default().size(32).build();
+        small.update(b"\x01\x02\x03");
+        assert_ne!(small.finalize(), Digest::default());
+    }
+
+    #[test]
+    fn non_ascii_keys() {
+        let mut hash = KnotHashBuilder::default().build();
+        hash.update(&[0xe9, b'!']);
+        assert_eq!(knot_hash("\u{e9}!"), hash.finalize());
+        assert_eq!(knot_hash("\u{1e9}!"), hash.finalize());
+    }
+
+    #[test]
+    fn batch() {
+        let keys = (0..100)
+            .map(|i| format!("flqrgnkx-{}", i * 7))
+            .collect::<Vec<_>>();
+        let expected = keys.iter().map(|k| knot_hash(k)).collect::<Vec<_>>();
+
+        let builder = KnotHashBuilder::default();
+        assert_eq!(builder.hash_all(&keys), expected);
+        assert_eq!(builder.hash_all_lanes(&keys), expected);
+
+        let small = KnotHashBuilder::default().size(16).rounds(3);
+        let keys = ["", "a", "bc", "de", "\u{ff}\u{ff}"];
+        let expected = keys
+            .iter()
+            .map(|key| {
+                let mut hash = small.build();
+                hash.update(key.as_bytes());
+                hash.finalize()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(small.hash_all_lanes(&keys), expected);
+    }
+
+    #[test]
+    fn in_place_reversal() {
+        let mut list = [0, 1, 2, 3, 4];
+        reverse(&mut list, 3, 4);
+        assert_eq!(list, [4, 3, 2, 1, 0]);
+
+        let mut list = [0, 1, 2];
+        reverse(&mut list, 1, 5);
+        assert_eq!(list, [0, 2, 1]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_size() {
+        KnotHashBuilder::default().size(100);
+    }
+
+    #[test]
+    fn build_hasher() {
+        let set = ["a", "b", "a"]
+            .iter()
+            .collect::<HashSet<_, KnotHashBuilder>>();
+
+        assert_eq!(set.len(), 2);
+
+        let mut hasher = KnotHashBuilder::default().build_hasher();
+        hasher.write(b"1,2,4");
+        assert_eq!(hasher.finish(), 0x63960835bcdc130f);
     }
 }
@@ -7,9 +7,13 @@ extern crate day25_parser;
 extern crate day7_parser;
 extern crate day8_parser;
 extern crate day9_parser;
+extern crate parse_error;
+extern crate vm;
 
+extern crate num_bigint;
 extern crate petgraph;
 extern crate rayon;
+extern crate time;
 
 pub mod day1;
 pub mod day2;
@@ -37,9 +41,10 @@ pub mod day23;
 pub mod day24;
 pub mod day25;
 
+pub mod grid;
+pub mod hex;
+pub mod input;
 pub mod knot_hasher;
-
-#[macro_export]
-macro_rules! load_input {
-    ($file:expr) => ({ include_str!(concat!("../../resources/", $file, ".txt"))})
-}
+pub mod registry;
+pub mod render;
+pub mod solution;
@@ -0,0 +1,129 @@
+use solution::Solution;
+
+use std::error::Error;
+
+use day1::Day1;
+use day2::Day2;
+use day3::Day3;
+use day4::Day4;
+use day5::Day5;
+use day6::Day6;
+use day7::Day7;
+use day8::Day8;
+use day9::Day9;
+use day10::Day10;
+use day11::Day11;
+use day12::Day12;
+use day13::Day13;
+use day14::Day14;
+use day15::Day15;
+use day16::Day16;
+use day17::Day17;
+use day18::Day18;
+use day19::Day19;
+use day20::Day20;
+use day21::Day21;
+use day22::Day22;
+use day23::Day23;
+use day24::Day24;
+use day25::Day25;
+
+type Solver = fn(&str) -> Result<String, Box<dyn Error>>;
+
+/// A type-erased `Solution`, so days can be iterated over generically.
+#[derive(Copy, Clone)]
+pub struct Day {
+    pub day: u32,
+    part1: Solver,
+    part2: Option<Solver>,
+}
+
+impl Day {
+    fn new<S: Solution>() -> Day {
+        Day {
+            day: S::DAY,
+            part1: solve_part1::<S>,
+            part2: if S::HAS_PART2 {
+                Some(solve_part2::<S>)
+            } else {
+                None
+            },
+        }
+    }
+
+    pub fn part1(&self, input: &str) -> Result<String, Box<dyn Error>> {
+        (self.part1)(input)
+    }
+
+    pub fn part2(&self, input: &str) -> Option<Result<String, Box<dyn Error>>> {
+        self.part2.map(|part2| part2(input))
+    }
+}
+
+fn solve_part1<S: Solution>(input: &str) -> Result<String, Box<dyn Error>> {
+    S::parse(input).map(|input| S::part1(&input).to_string())
+}
+
+fn solve_part2<S: Solution>(input: &str) -> Result<String, Box<dyn Error>> {
+    S::parse(input).map(|input| S::part2(&input).to_string())
+}
+
+pub fn days() -> Vec<Day> {
+    vec![
+        Day::new::<Day1>(),
+        Day::new::<Day2>(),
+        Day::new::<Day3>(),
+        Day::new::<Day4>(),
+        Day::new::<Day5>(),
+        Day::new::<Day6>(),
+        Day::new::<Day7>(),
+        Day::new::<Day8>(),
+        Day::new::<Day9>(),
+        Day::new::<Day10>(),
+        Day::new::<Day11>(),
+        Day::new::<Day12>(),
+        Day::new::<Day13>(),
+        Day::new::<Day14>(),
+        Day::new::<Day15>(),
+        Day::new::<Day16>(),
+        Day::new::<Day17>(),
+        Day::new::<Day18>(),
+        Day::new::<Day19>(),
+        Day::new::<Day20>(),
+        Day::new::<Day21>(),
+        Day::new::<Day22>(),
+        Day::new::<Day23>(),
+        Day::new::<Day24>(),
+        Day::new::<Day25>(),
+    ]
+}
+
+pub fn day(day: u32) -> Option<Day> {
+    days().into_iter().find(|d| d.day == day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_days_registered() {
+        let days = days().iter().map(|d| d.day).collect::<Vec<_>>();
+
+        assert_eq!(days, (1..26).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn only_last_day_has_no_part2() {
+        assert!(day(24).unwrap().part2.is_some());
+        assert!(day(25).unwrap().part2.is_none());
+    }
+
+    #[test]
+    fn solve_from_raw_input() {
+        let day1 = day(1).unwrap();
+
+        assert_eq!(day1.part1("1122\n").unwrap(), "3");
+        assert_eq!(day1.part2("1212\n").unwrap().unwrap(), "6");
+    }
+}
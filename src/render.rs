@@ -0,0 +1,357 @@
+//! Pictures of the grids of days 14, 21 and 22, as netpbm images or text.
+//!
+//! An `Image` only holds the index of each cell's value; a `Palette` gives
+//! every index its colour and glyph.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+pub type Rgb = [u8; 3];
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    cells: Vec<usize>,
+}
+
+impl Image {
+    /// An image where `cell(x, y)` is the palette index of every pixel.
+    pub fn from_fn<F: Fn(usize, usize) -> usize>(width: usize, height: usize, cell: F) -> Image {
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| cell(x, y))
+            .collect();
+
+        Image {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    /// Index 1 for a set pixel, 0 otherwise.
+    pub fn from_rows(rows: &[Vec<bool>]) -> Image {
+        let width = rows.first().map_or(0, |row| row.len());
+
+        Image::from_fn(width, rows.len(), |x, y| rows[y][x] as usize)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> usize {
+        self.cells[y * self.width + x]
+    }
+
+    fn rows(&self) -> impl Iterator<Item = &[usize]> {
+        self.cells.chunks(self.width.max(1)).take(self.height)
+    }
+
+    pub fn write<W: Write>(
+        &self,
+        format: Format,
+        palette: &Palette,
+        out: &mut W,
+    ) -> io::Result<()> {
+        match format {
+            Format::Pbm => writeln!(out, "P1\n{} {}", self.width, self.height)?,
+            Format::Pgm => writeln!(out, "P2\n{} {}\n255", self.width, self.height)?,
+            Format::Ppm => writeln!(out, "P3\n{} {}\n255", self.width, self.height)?,
+            Format::Ascii => (),
+        }
+
+        for row in self.rows() {
+            let pixels = row.iter().map(|&cell| match format {
+                Format::Pbm => if cell != 0 { "1" } else { "0" }.to_owned(),
+                Format::Pgm => palette.gray(cell).to_string(),
+                Format::Ppm => {
+                    let [r, g, b] = palette.color(cell);
+                    format!("{} {} {}", r, g, b)
+                }
+                Format::Ascii => palette.glyph(cell).to_string(),
+            });
+
+            let separator = if format == Format::Ascii { "" } else { " " };
+
+            writeln!(out, "{}", pixels.collect::<Vec<_>>().join(separator))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn to_ascii(&self, palette: &Palette) -> String {
+        let mut text = Vec::new();
+
+        self.write(Format::Ascii, palette, &mut text)
+            .expect("writing to memory can't fail");
+
+        String::from_utf8(text).expect("glyphs are valid utf-8")
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Format {
+    /// Plain bitmap, any non-zero index being black.
+    Pbm,
+    /// Plain graymap, from the luminance of the palette colours.
+    Pgm,
+    /// Plain pixmap, in the palette colours.
+    Ppm,
+    /// A line of glyphs per row.
+    Ascii,
+}
+
+impl Format {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Pbm => "pbm",
+            Format::Pgm => "pgm",
+            Format::Ppm => "ppm",
+            Format::Ascii => "txt",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "pbm" => Ok(Format::Pbm),
+            "pgm" => Ok(Format::Pgm),
+            "ppm" => Ok(Format::Ppm),
+            "ascii" => Ok(Format::Ascii),
+            _ => Err(format!(
+                "Invalid format: {}, expected pbm, pgm, ppm or ascii",
+                s
+            )),
+        }
+    }
+}
+
+/// The colour and glyph of every index, repeating when there are more indices
+/// than entries.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Palette {
+    colors: Vec<Rgb>,
+    glyphs: Vec<char>,
+}
+
+impl Default for Palette {
+    /// White `.` and black `#`, as in the puzzles.
+    fn default() -> Palette {
+        Palette::new(vec![[255, 255, 255], [0, 0, 0]], ".#")
+    }
+}
+
+impl Palette {
+    /// # Panics
+    ///
+    /// If `colors` or `glyphs` is empty.
+    pub fn new(colors: Vec<Rgb>, glyphs: &str) -> Palette {
+        let glyphs = glyphs.chars().collect::<Vec<_>>();
+
+        assert!(
+            !colors.is_empty() && !glyphs.is_empty(),
+            "a palette needs colours and glyphs"
+        );
+
+        Palette { colors, glyphs }
+    }
+
+    /// White and `.` for index 0, then `n` hues and letters spread apart, so
+    /// neighbouring regions stand out.
+    pub fn distinct(n: usize) -> Palette {
+        let mut colors = vec![[255, 255, 255]];
+
+        colors.extend((0..n.max(1)).map(|i| hue(i as f64 * 0.618_033_988_75 % 1.0)));
+
+        Palette::new(
+            colors,
+            ".ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+        )
+    }
+
+    /// Replaces the glyphs, the first one standing for index 0.
+    pub fn with_glyphs(self, glyphs: &str) -> Palette {
+        Palette::new(self.colors, glyphs)
+    }
+
+    pub fn color(&self, index: usize) -> Rgb {
+        if index == 0 || self.colors.len() == 1 {
+            self.colors[0]
+        } else {
+            // Index 0 is kept for the background when the colours repeat.
+            self.colors[1 + (index - 1) % (self.colors.len() - 1)]
+        }
+    }
+
+    pub fn gray(&self, index: usize) -> u8 {
+        let [r, g, b] = self.color(index);
+
+        (0.299 * f64::from(r) + 0.587 * f64::from(g) + 0.114 * f64::from(b)).round() as u8
+    }
+
+    pub fn glyph(&self, index: usize) -> char {
+        if index == 0 || self.glyphs.len() == 1 {
+            self.glyphs[0]
+        } else {
+            self.glyphs[1 + (index - 1) % (self.glyphs.len() - 1)]
+        }
+    }
+}
+
+/// A saturated colour, `h` going from 0 to 1 around the colour wheel.
+fn hue(h: f64) -> Rgb {
+    let channel = |offset: f64| {
+        let x = ((h + offset) * 6.0) % 6.0;
+        let level = (x - 3.0).abs() - 1.0;
+
+        (level.clamp(0.0, 1.0) * 200.0 + 30.0) as u8
+    };
+
+    [channel(0.0), channel(2.0 / 3.0), channel(1.0 / 3.0)]
+}
+
+/// Numbered images of a simulation, written to a directory as `prefix-0042.ppm`.
+#[derive(Debug)]
+pub struct Frames {
+    directory: PathBuf,
+    prefix: String,
+    format: Format,
+    palette: Palette,
+    every: usize,
+    written: usize,
+}
+
+impl Frames {
+    pub fn new<P: Into<PathBuf>>(directory: P, prefix: &str) -> Frames {
+        Frames {
+            directory: directory.into(),
+            prefix: prefix.to_owned(),
+            format: Format::Ppm,
+            palette: Palette::default(),
+            every: 1,
+            written: 0,
+        }
+    }
+
+    pub fn format(mut self, format: Format) -> Frames {
+        self.format = format;
+        self
+    }
+
+    pub fn palette(mut self, palette: Palette) -> Frames {
+        self.palette = palette;
+        self
+    }
+
+    /// Only keeps the steps that are multiples of `every`.
+    pub fn every(mut self, every: usize) -> Frames {
+        self.every = every.max(1);
+        self
+    }
+
+    /// Number of frames written so far.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Writes the frame of `step`, if kept, `image` being only drawn then.
+    pub fn capture<F: FnOnce() -> Image>(&mut self, step: usize, image: F) -> io::Result<()> {
+        if !step.is_multiple_of(self.every) {
+            return Ok(());
+        }
+
+        fs::create_dir_all(&self.directory)?;
+
+        let path = self.directory.join(format!(
+            "{}-{:04}.{}",
+            self.prefix,
+            step,
+            self.format.extension()
+        ));
+
+        let mut out = BufWriter::new(File::create(path)?);
+        image().write(self.format, &self.palette, &mut out)?;
+        out.flush()?;
+
+        self.written += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    fn checker() -> Image {
+        Image::from_rows(&[vec![true, false, true], vec![false, true, false]])
+    }
+
+    fn render(format: Format, palette: &Palette) -> String {
+        let mut out = Vec::new();
+        checker().write(format, palette, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn netpbm() {
+        let palette = Palette::new(vec![[255, 255, 255], [255, 0, 0]], ".#");
+
+        assert_eq!(render(Format::Pbm, &palette), "P1\n3 2\n1 0 1\n0 1 0\n");
+        assert_eq!(
+            render(Format::Pgm, &palette),
+            "P2\n3 2\n255\n76 255 76\n255 76 255\n"
+        );
+        assert_eq!(
+            render(Format::Ppm, &palette),
+            "P3\n3 2\n255\n255 0 0 255 255 255 255 0 0\n255 255 255 255 0 0 255 255 255\n"
+        );
+    }
+
+    #[test]
+    fn ascii() {
+        assert_eq!(checker().to_ascii(&Palette::default()), "#.#\n.#.\n");
+        assert_eq!(
+            checker().to_ascii(&Palette::default().with_glyphs(" o")),
+            "o o\n o \n"
+        );
+
+        let regions = Image::from_fn(4, 1, |x, _| x);
+        let palette = Palette::distinct(2);
+        assert_eq!(regions.to_ascii(&palette), ".ABC\n");
+        assert_ne!(palette.color(1), palette.color(2));
+        assert_eq!(palette.color(1), palette.color(3));
+    }
+
+    #[test]
+    fn frames() {
+        let dir = env::temp_dir().join(format!("aoc2017-frames-{}", std::process::id()));
+
+        let mut frames = Frames::new(&dir, "checker").format(Format::Pbm).every(2);
+
+        for step in 0..5 {
+            frames.capture(step, checker).unwrap();
+        }
+
+        assert_eq!(frames.written(), 3);
+        assert_eq!(
+            fs::read_to_string(dir.join("checker-0004.pbm")).unwrap(),
+            "P1\n3 2\n1 0 1\n0 1 0\n"
+        );
+        assert!(!dir.join("checker-0003.pbm").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,21 @@
+use std::error::Error;
+use std::fmt::Display;
+
+/// A puzzle of the advent calendar, from its raw input to both answers.
+pub trait Solution {
+    /// Day of december the puzzle was released.
+    const DAY: u32;
+
+    /// Day 25 only has one puzzle to solve.
+    const HAS_PART2: bool = true;
+
+    type Input;
+    type Output1: Display;
+    type Output2: Display;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>>;
+
+    fn part1(input: &Self::Input) -> Self::Output1;
+
+    fn part2(input: &Self::Input) -> Self::Output2;
+}
@@ -0,0 +1,284 @@
+//! A text format for programs, with labels, comments and symbolic jumps:
+//!
+//! ```text
+//! ; counts down from 3
+//!     set a 3
+//! loop:
+//!     sub a 1
+//!     jnz a loop  # back while a != 0
+//! ```
+//!
+//! A jump to a label is lowered to the relative offset the puzzles use, and
+//! raw offsets are still accepted.
+
+use std::collections::HashMap;
+
+use nom::IResult;
+use parse_error::{self, ErrorKind, ParseError};
+
+use {Instruction, Value};
+
+/// Where a comment starts, until the end of the line.
+const COMMENT: &[char] = &[';', '#'];
+
+/// Labels are identifiers, longer than a letter so they can't be mistaken for a
+/// register.
+fn is_label(word: &str) -> bool {
+    let mut chars = word.chars();
+
+    word.len() > 1
+        && chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// An instruction, still in text.
+struct Statement<'a> {
+    line: usize,
+    text: &'a str,
+    /// Column of the statement in `text`, starting at 0.
+    column: usize,
+    statement: &'a str,
+}
+
+/// Assembles `source` with `line`, the parser of a dialect's instructions.
+pub fn assemble<F>(source: &str, line: F) -> Result<Vec<Instruction>, ParseError>
+where
+    F: Fn(&[u8]) -> IResult<&[u8], Instruction>,
+{
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+
+    for (i, text) in source.lines().enumerate() {
+        let code = text.split(COMMENT).next().unwrap_or("");
+        let mut column = code.len() - code.trim_start().len();
+        let mut statement = code.trim();
+
+        if let Some(colon) = statement.find(':') {
+            let label = &statement[..colon];
+            let invalid = |reason: String| {
+                Err(ParseError::new(
+                    i + 1,
+                    column + 1,
+                    text,
+                    ErrorKind::Invalid(reason),
+                ))
+            };
+
+            if !is_label(label) {
+                return invalid(format!("invalid label `{}`", label));
+            }
+
+            if labels.insert(label, statements.len() as i64).is_some() {
+                return invalid(format!("duplicate label `{}`", label));
+            }
+
+            let rest = &statement[colon + 1..];
+            column += colon + 1 + rest.len() - rest.trim_start().len();
+            statement = rest.trim();
+        }
+
+        if !statement.is_empty() {
+            statements.push(Statement {
+                line: i + 1,
+                text,
+                column,
+                statement,
+            });
+        }
+    }
+
+    statements
+        .iter()
+        .enumerate()
+        .map(|(address, s)| {
+            let mut statement = s.statement.to_owned();
+
+            let is_jump = s.statement.starts_with("jgz ") || s.statement.starts_with("jnz ");
+            let target = s.statement.split_whitespace().last().unwrap_or("");
+
+            if is_jump && is_label(target) {
+                let offset = match labels.get(target) {
+                    Some(&target) => target - address as i64,
+                    None => {
+                        return Err(ParseError::new(
+                            s.line,
+                            s.column + s.statement.len() - target.len() + 1,
+                            s.text,
+                            ErrorKind::Invalid(format!("undefined label `{}`", target)),
+                        ))
+                    }
+                };
+
+                statement.truncate(s.statement.len() - target.len());
+                statement.push_str(&offset.to_string());
+            }
+
+            parse_error::parse_complete(&statement, &line)
+                .map_err(|err| err.shifted(s.line, s.column, s.text))
+        })
+        .collect()
+}
+
+/// The address a jump with a literal offset lands on, if it is in the program
+/// or right after it.
+fn target(program: &[Instruction], address: usize) -> Option<usize> {
+    let offset = match program[address] {
+        Instruction::Jgz(_, Value::Literal(offset))
+        | Instruction::Jnz(_, Value::Literal(offset)) => offset,
+        _ => return None,
+    };
+
+    let target = address as i64 + offset;
+
+    if target >= 0 && target <= program.len() as i64 {
+        Some(target as usize)
+    } else {
+        None
+    }
+}
+
+/// Prints `program` in the format `assemble` reads, jumps going to labels named
+/// after the address they mark.
+pub fn disassemble(program: &[Instruction]) -> String {
+    let targets = (0..program.len())
+        .filter_map(|address| target(program, address))
+        .collect::<Vec<_>>();
+
+    let label = |address: usize| format!("l{}", address);
+
+    let mut text = String::new();
+
+    for address in 0..=program.len() {
+        if targets.contains(&address) {
+            text.push_str(&label(address));
+            text.push_str(":\n");
+        }
+
+        if address == program.len() {
+            break;
+        }
+
+        let instruction = match (program[address], target(program, address)) {
+            (Instruction::Jgz(val, _), Some(target)) => format!("jgz {} {}", val, label(target)),
+            (Instruction::Jnz(val, _), Some(target)) => format!("jnz {} {}", val, label(target)),
+            (instruction, _) => instruction.to_string(),
+        };
+
+        text.push_str("    ");
+        text.push_str(&instruction);
+        text.push('\n');
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use grammar::*;
+    use Instruction::*;
+    use Value::*;
+
+    named!(
+        line<Instruction>,
+        alt!(snd | rcv | set | add | sub | mul | modulo | jgz | jnz)
+    );
+
+    const COUNTDOWN: &str = "; counts down from 3
+    set a 3
+loop:
+    sub a 1
+    snd a       # the remaining count
+    jnz a loop
+    jgz 1 end
+    set b 1
+end:";
+
+    #[test]
+    fn labels_and_comments() {
+        assert_eq!(
+            assemble(COUNTDOWN, line),
+            Ok(vec![
+                Set('a', Literal(3)),
+                Sub('a', Literal(1)),
+                Snd(Register('a')),
+                Jnz(Register('a'), Literal(-2)),
+                Jgz(Literal(1), Literal(2)),
+                Set('b', Literal(1)),
+            ])
+        );
+    }
+
+    #[test]
+    fn label_on_instruction() {
+        assert_eq!(
+            assemble("start: add a 1\njgz 1 start\njgz a -7", line),
+            Ok(vec![
+                Add('a', Literal(1)),
+                Jgz(Literal(1), Literal(-1)),
+                Jgz(Register('a'), Literal(-7)),
+            ])
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            assemble("set a 1\n  jnz a nowhere", line),
+            Err(ParseError::new(
+                2,
+                9,
+                "  jnz a nowhere",
+                ErrorKind::Invalid("undefined label `nowhere`".to_owned())
+            ))
+        );
+
+        assert_eq!(
+            assemble("a: set a 1", line).unwrap_err().kind,
+            ErrorKind::Invalid("invalid label `a`".to_owned())
+        );
+
+        assert_eq!(
+            assemble("top:\ntop:", line).unwrap_err().kind,
+            ErrorKind::Invalid("duplicate label `top`".to_owned())
+        );
+
+        assert_eq!(
+            assemble("top: set a !", line),
+            Err(ParseError::new(1, 6, "top: set a !", ErrorKind::Syntax))
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let program = assemble(COUNTDOWN, line).unwrap();
+        let text = disassemble(&program);
+
+        assert_eq!(
+            text,
+            "    set a 3
+l1:
+    sub a 1
+    snd a
+    jnz a l1
+    jgz 1 l6
+    set b 1
+l6:
+"
+        );
+        assert_eq!(assemble(&text, line), Ok(program));
+    }
+
+    #[test]
+    fn unresolved_jumps_keep_their_offset() {
+        let program = vec![
+            Jgz(Literal(1), Literal(5)),
+            Jgz(Register('a'), Register('b')),
+        ];
+
+        assert_eq!(disassemble(&program), "    jgz 1 5\n    jgz a b\n");
+    }
+}
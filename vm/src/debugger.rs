@@ -0,0 +1,363 @@
+//! Stepping, breakpoints and tracing over a `Machine`.
+
+use std::error::Error;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use {Execute, Halt, Io, LetterRegisters, Machine, Mailbox, Register, Registers};
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Condition {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Condition {
+    pub fn holds(self, a: i64, b: i64) -> bool {
+        match self {
+            Condition::Equal => a == b,
+            Condition::NotEqual => a != b,
+            Condition::Less => a < b,
+            Condition::LessOrEqual => a <= b,
+            Condition::Greater => a > b,
+            Condition::GreaterOrEqual => a >= b,
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Condition::Equal => "==",
+            Condition::NotEqual => "!=",
+            Condition::Less => "<",
+            Condition::LessOrEqual => "<=",
+            Condition::Greater => ">",
+            Condition::GreaterOrEqual => ">=",
+        })
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Breakpoint {
+    /// Stops before executing the instruction at this address.
+    ProgramCounter(i64),
+    /// Stops as soon as the register satisfies the condition.
+    Register(Register, Condition, i64),
+}
+
+impl Breakpoint {
+    fn hit<I: Execute, R: Registers>(&self, machine: &Machine<I, R>) -> bool {
+        match *self {
+            Breakpoint::ProgramCounter(pc) => machine.program_counter() == pc,
+            Breakpoint::Register(reg, cond, val) => cond.holds(machine.get(reg), val),
+        }
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Breakpoint::ProgramCounter(pc) => write!(f, "{}", pc),
+            Breakpoint::Register(reg, cond, val) => write!(f, "{} {} {}", reg, cond, val),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ParseBreakpointError(String);
+
+impl fmt::Display for ParseBreakpointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid breakpoint {:?}, expected <pc> or <register> <op> <value>",
+            self.0
+        )
+    }
+}
+
+impl Error for ParseBreakpointError {}
+
+/// Parses `12` or `a >= 3`.
+impl FromStr for Breakpoint {
+    type Err = ParseBreakpointError;
+
+    fn from_str(s: &str) -> Result<Self, ParseBreakpointError> {
+        let err = || ParseBreakpointError(s.to_owned());
+
+        let words = s.split_whitespace().collect::<Vec<_>>();
+
+        match words[..] {
+            [pc] => pc
+                .parse()
+                .map(Breakpoint::ProgramCounter)
+                .map_err(|_| err()),
+            [reg, cond, val] => {
+                let mut chars = reg.chars();
+                // Registers are named `a` to `z`, as `LetterRegisters` expects.
+                let reg = match (chars.next(), chars.next()) {
+                    (Some(reg), None) if reg.is_ascii_lowercase() => reg,
+                    _ => return Err(err()),
+                };
+
+                let cond = match cond {
+                    "==" => Condition::Equal,
+                    "!=" => Condition::NotEqual,
+                    "<" => Condition::Less,
+                    "<=" => Condition::LessOrEqual,
+                    ">" => Condition::Greater,
+                    ">=" => Condition::GreaterOrEqual,
+                    _ => return Err(err()),
+                };
+
+                Ok(Breakpoint::Register(
+                    reg,
+                    cond,
+                    val.parse().map_err(|_| err())?,
+                ))
+            }
+            _ => Err(err()),
+        }
+    }
+}
+
+/// The registers that differ, as ` a=1 b=2`.
+fn changes(before: &[(Register, i64)], after: &[(Register, i64)]) -> String {
+    let value = |values: &[(Register, i64)], reg| {
+        values
+            .iter()
+            .find(|&&(r, _)| r == reg)
+            .map_or(0, |&(_, v)| v)
+    };
+
+    let mut registers = before
+        .iter()
+        .chain(after)
+        .map(|&(r, _)| r)
+        .collect::<Vec<_>>();
+    registers.sort();
+    registers.dedup();
+
+    registers
+        .into_iter()
+        .filter(|&r| value(before, r) != value(after, r))
+        .map(|r| format!(" {}={}", r, value(after, r)))
+        .collect()
+}
+
+/// Why `Debugger::run` returned.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Stop {
+    /// The breakpoint at this index was hit.
+    Breakpoint(usize),
+    Halt(Halt),
+}
+
+pub struct Debugger<I, R = LetterRegisters, O = Mailbox> {
+    machine: Machine<I, R>,
+    io: O,
+    breakpoints: Vec<Breakpoint>,
+    trace: Option<Box<dyn Write>>,
+    steps: u64,
+}
+
+impl<I: Execute + fmt::Display, R: Registers, O: Io> Debugger<I, R, O> {
+    pub fn new(machine: Machine<I, R>, io: O) -> Debugger<I, R, O> {
+        Debugger {
+            machine,
+            io,
+            breakpoints: Vec::new(),
+            trace: None,
+            steps: 0,
+        }
+    }
+
+    pub fn machine(&self) -> &Machine<I, R> {
+        &self.machine
+    }
+
+    pub fn machine_mut(&mut self) -> &mut Machine<I, R> {
+        &mut self.machine
+    }
+
+    pub fn io(&self) -> &O {
+        &self.io
+    }
+
+    pub fn io_mut(&mut self) -> &mut O {
+        &mut self.io
+    }
+
+    /// Number of instructions executed so far.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        self.breakpoints.push(breakpoint);
+        self.breakpoints.len() - 1
+    }
+
+    pub fn remove_breakpoint(&mut self, index: usize) -> Option<Breakpoint> {
+        if index < self.breakpoints.len() {
+            Some(self.breakpoints.remove(index))
+        } else {
+            None
+        }
+    }
+
+    /// Writes a line per executed instruction: step, address, instruction and
+    /// the registers it changed.
+    pub fn trace_to<W: Write + 'static>(&mut self, out: W) {
+        self.trace = Some(Box::new(out));
+    }
+
+    pub fn stop_trace(&mut self) {
+        self.trace = None;
+    }
+
+    pub fn step(&mut self) -> Result<I, Halt> {
+        let pc = self.machine.program_counter();
+        let before = self
+            .trace
+            .as_ref()
+            .map(|_| self.machine.registers().values());
+
+        let instruction = self.machine.step(&mut self.io)?;
+        self.steps += 1;
+
+        if let (Some(out), Some(before)) = (self.trace.as_mut(), before) {
+            let changes = changes(&before, &self.machine.registers().values());
+
+            let line = format!(
+                "{:>8} {:>4}: {:<12}{}",
+                self.steps,
+                pc,
+                instruction.to_string(),
+                changes
+            );
+
+            if writeln!(out, "{}", line.trim_end()).is_err() {
+                self.trace = None;
+            }
+        }
+
+        Ok(instruction)
+    }
+
+    /// Steps until a breakpoint is hit or the machine halts, executing at least
+    /// one instruction so a stopped program can be resumed.
+    pub fn run(&mut self) -> Stop {
+        loop {
+            if let Err(halt) = self.step() {
+                return Stop::Halt(halt);
+            }
+
+            if let Some(index) = self.breakpoints.iter().position(|b| b.hit(&self.machine)) {
+                return Stop::Breakpoint(index);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+    use std::io;
+    use std::rc::Rc;
+
+    use Instruction::{self, *};
+    use Value::*;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn countdown() -> Debugger<Instruction> {
+        Debugger::new(
+            Machine::new(vec![
+                Set('a', Literal(3)),
+                Sub('a', Literal(1)),
+                Snd(Register('a')),
+                Jnz(Register('a'), Literal(-2)),
+            ]),
+            Mailbox::default(),
+        )
+    }
+
+    #[test]
+    fn parse_breakpoint() {
+        assert_eq!("3".parse(), Ok(Breakpoint::ProgramCounter(3)));
+        assert_eq!(
+            "b <= -2".parse(),
+            Ok(Breakpoint::Register('b', Condition::LessOrEqual, -2))
+        );
+        assert!("b = 2".parse::<Breakpoint>().is_err());
+        assert!("Z == 0".parse::<Breakpoint>().is_err());
+    }
+
+    #[test]
+    fn breakpoint_on_program_counter() {
+        let mut debugger = countdown();
+        debugger.add_breakpoint(Breakpoint::ProgramCounter(2));
+
+        assert_eq!(debugger.run(), Stop::Breakpoint(0));
+        assert_eq!(debugger.machine().get('a'), 2);
+
+        assert_eq!(debugger.run(), Stop::Breakpoint(0));
+        assert_eq!(debugger.machine().get('a'), 1);
+        assert_eq!(debugger.io().outbox, vec![2]);
+    }
+
+    #[test]
+    fn breakpoint_on_register() {
+        let mut debugger = countdown();
+        debugger.add_breakpoint("a == 0".parse().unwrap());
+
+        assert_eq!(debugger.run(), Stop::Breakpoint(0));
+        assert_eq!(debugger.machine().program_counter(), 2);
+        assert_eq!(debugger.steps(), 8);
+
+        assert!(debugger.remove_breakpoint(0).is_some());
+        assert_eq!(debugger.run(), Stop::Halt(Halt::Exit));
+        assert_eq!(debugger.io().outbox, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn trace() {
+        let buffer = Buffer::default();
+
+        let mut debugger = countdown();
+        debugger.trace_to(buffer.clone());
+
+        for _ in 0..3 {
+            debugger.step().unwrap();
+        }
+
+        assert_eq!(
+            String::from_utf8(buffer.0.borrow().clone()).unwrap(),
+            "       1    0: set a 3      a=3\n       2    1: sub a 1      a=2\n       3    2: snd a\n"
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Parsers for every mnemonic, a dialect picking the ones it accepts:
+//!
+//! ```text
+//! named!(line<Instruction>, alt!(set | sub | mul | jnz));
+//! ```
+
+use nom::{anychar, digit, space};
+use std::str;
+
+use {Instruction, Register, Value};
+
+named!(pub register<Register>, verify!(anychar, |c: char| c.is_ascii_lowercase()));
+
+named!(
+    pub literal<i64>,
+    map_res!(
+        map_res!(recognize!(pair!(opt!(tag!("-")), digit)), str::from_utf8),
+        str::parse
+    )
+);
+
+named!(
+    pub value<Value>,
+    alt!(map!(register, Value::Register) | map!(literal, Value::Literal))
+);
+
+named!(
+    pub snd<Instruction>,
+    do_parse!(tag!("snd ") >> val: value >> (Instruction::Snd(val)))
+);
+
+named!(
+    pub rcv<Instruction>,
+    do_parse!(tag!("rcv ") >> reg: register >> (Instruction::Rcv(reg)))
+);
+
+named!(
+    pub set<Instruction>,
+    do_parse!(tag!("set ") >> reg: register >> space >> val: value >> (Instruction::Set(reg, val)))
+);
+
+named!(
+    pub add<Instruction>,
+    do_parse!(tag!("add ") >> reg: register >> space >> val: value >> (Instruction::Add(reg, val)))
+);
+
+named!(
+    pub sub<Instruction>,
+    do_parse!(tag!("sub ") >> reg: register >> space >> val: value >> (Instruction::Sub(reg, val)))
+);
+
+named!(
+    pub mul<Instruction>,
+    do_parse!(tag!("mul ") >> reg: register >> space >> val: value >> (Instruction::Mul(reg, val)))
+);
+
+named!(
+    pub modulo<Instruction>,
+    do_parse!(tag!("mod ") >> reg: register >> space >> val: value >> (Instruction::Mod(reg, val)))
+);
+
+named!(
+    pub jgz<Instruction>,
+    do_parse!(
+        tag!("jgz ") >> val: value >> space >> offset: value >> (Instruction::Jgz(val, offset))
+    )
+);
+
+named!(
+    pub jnz<Instruction>,
+    do_parse!(
+        tag!("jnz ") >> val: value >> space >> offset: value >> (Instruction::Jnz(val, offset))
+    )
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use nom::IResult::Done;
+
+    const EMPTY: &[u8] = b"";
+
+    #[test]
+    fn test_value() {
+        assert_eq!(value(b"a"), Done(EMPTY, Value::Register('a')));
+        assert_eq!(value(b"-12"), Done(EMPTY, Value::Literal(-12)));
+        assert!(value(b"A").is_err());
+    }
+
+    #[test]
+    fn test_mnemonics() {
+        assert_eq!(
+            snd(b"snd 3"),
+            Done(EMPTY, Instruction::Snd(Value::Literal(3)))
+        );
+        assert_eq!(rcv(b"rcv b"), Done(EMPTY, Instruction::Rcv('b')));
+        assert_eq!(
+            modulo(b"mod a b"),
+            Done(EMPTY, Instruction::Mod('a', Value::Register('b')))
+        );
+        assert_eq!(
+            jgz(b"jgz 1 -2"),
+            Done(
+                EMPTY,
+                Instruction::Jgz(Value::Literal(1), Value::Literal(-2))
+            )
+        );
+    }
+}
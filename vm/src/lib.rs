@@ -0,0 +1,422 @@
+//! A small register machine running the assembly of days 18 and 23.
+//!
+//! A dialect is a parser picking its mnemonics from [`grammar`], a register
+//! file implementing [`Registers`], and an [`Io`] giving `snd` and `rcv` their
+//! meaning.
+
+#[macro_use]
+extern crate nom;
+extern crate parse_error;
+
+pub mod assembler;
+pub mod debugger;
+pub mod grammar;
+pub mod optimizer;
+pub mod scheduler;
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+pub type Register = char;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Value {
+    Register(Register),
+    Literal(i64),
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Instruction {
+    Snd(Value),
+    Rcv(Register),
+    Set(Register, Value),
+    Add(Register, Value),
+    Sub(Register, Value),
+    Mul(Register, Value),
+    Mod(Register, Value),
+    Jgz(Value, Value),
+    Jnz(Value, Value),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Register(reg) => write!(f, "{}", reg),
+            Value::Literal(val) => write!(f, "{}", val),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instruction::Snd(val) => write!(f, "snd {}", val),
+            Instruction::Rcv(reg) => write!(f, "rcv {}", reg),
+            Instruction::Set(reg, val) => write!(f, "set {} {}", reg, val),
+            Instruction::Add(reg, val) => write!(f, "add {} {}", reg, val),
+            Instruction::Sub(reg, val) => write!(f, "sub {} {}", reg, val),
+            Instruction::Mul(reg, val) => write!(f, "mul {} {}", reg, val),
+            Instruction::Mod(reg, val) => write!(f, "mod {} {}", reg, val),
+            Instruction::Jgz(val, offset) => write!(f, "jgz {} {}", val, offset),
+            Instruction::Jnz(val, offset) => write!(f, "jnz {} {}", val, offset),
+        }
+    }
+}
+
+/// Why a machine stopped running.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Halt {
+    /// The program counter left the program.
+    Exit,
+    /// A `rcv` got no value; it is executed again when the machine resumes.
+    Receive,
+    /// A `snd` couldn't deliver its value; it is executed again when the
+    /// machine resumes.
+    Send,
+    /// A `mod` by zero, the program counter still points to it.
+    DivisionByZero,
+}
+
+impl fmt::Display for Halt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Halt::Exit => write!(f, "program exited"),
+            Halt::Receive => write!(f, "waiting on rcv"),
+            Halt::Send => write!(f, "waiting on snd"),
+            Halt::DivisionByZero => write!(f, "division by zero"),
+        }
+    }
+}
+
+/// Where the program counter goes after an instruction.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Flow {
+    Next,
+    Jump(i64),
+}
+
+/// An instruction set a `Machine` can run.
+pub trait Execute: Copy {
+    fn execute<R: Registers, O: Io>(self, registers: &mut R, io: &mut O) -> Result<Flow, Halt>;
+}
+
+impl Execute for Instruction {
+    fn execute<R: Registers, O: Io>(self, registers: &mut R, io: &mut O) -> Result<Flow, Halt> {
+        match self {
+            Instruction::Snd(val) => {
+                if !io.send(registers.value(val)) {
+                    return Err(Halt::Send);
+                }
+            }
+            Instruction::Rcv(reg) => {
+                let val = io.receive(registers.get(reg)).ok_or(Halt::Receive)?;
+                registers.set(reg, val);
+            }
+            Instruction::Set(reg, val) => apply(registers, reg, val, |_, b| b),
+            Instruction::Add(reg, val) => apply(registers, reg, val, |a, b| a + b),
+            Instruction::Sub(reg, val) => apply(registers, reg, val, |a, b| a - b),
+            Instruction::Mul(reg, val) => apply(registers, reg, val, |a, b| a * b),
+            Instruction::Mod(reg, val) => {
+                if registers.value(val) == 0 {
+                    return Err(Halt::DivisionByZero);
+                }
+
+                apply(registers, reg, val, |a, b| a % b)
+            }
+            Instruction::Jgz(val, offset) => {
+                if registers.value(val) > 0 {
+                    return Ok(Flow::Jump(registers.value(offset)));
+                }
+            }
+            Instruction::Jnz(val, offset) => {
+                if registers.value(val) != 0 {
+                    return Ok(Flow::Jump(registers.value(offset)));
+                }
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+}
+
+fn apply<R: Registers>(registers: &mut R, reg: Register, val: Value, op: fn(i64, i64) -> i64) {
+    let val = op(registers.get(reg), registers.value(val));
+    registers.set(reg, val);
+}
+
+/// A register file, registers being 0 until set.
+pub trait Registers {
+    fn get(&self, reg: Register) -> i64;
+
+    fn set(&mut self, reg: Register, value: i64);
+
+    /// The registers holding something else than 0, in order.
+    fn values(&self) -> Vec<(Register, i64)>;
+
+    fn value(&self, value: Value) -> i64 {
+        match value {
+            Value::Register(reg) => self.get(reg),
+            Value::Literal(val) => val,
+        }
+    }
+}
+
+/// The 26 registers `a` to `z`.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub struct LetterRegisters([i64; 26]);
+
+impl LetterRegisters {
+    fn index(reg: Register) -> usize {
+        assert!(reg.is_ascii_lowercase(), "invalid register {:?}", reg);
+
+        (reg as u8 - b'a') as usize
+    }
+}
+
+impl Registers for LetterRegisters {
+    fn get(&self, reg: Register) -> i64 {
+        self.0[LetterRegisters::index(reg)]
+    }
+
+    fn set(&mut self, reg: Register, value: i64) {
+        self.0[LetterRegisters::index(reg)] = value;
+    }
+
+    fn values(&self) -> Vec<(Register, i64)> {
+        (b'a'..)
+            .map(|c| c as char)
+            .zip(self.0.iter().cloned())
+            .filter(|&(_, v)| v != 0)
+            .collect()
+    }
+}
+
+/// Any register name, for dialects outside `a` to `z`.
+impl Registers for HashMap<Register, i64> {
+    fn get(&self, reg: Register) -> i64 {
+        *HashMap::get(self, &reg).unwrap_or(&0)
+    }
+
+    fn set(&mut self, reg: Register, value: i64) {
+        self.insert(reg, value);
+    }
+
+    fn values(&self) -> Vec<(Register, i64)> {
+        let mut values = self
+            .iter()
+            .map(|(&r, &v)| (r, v))
+            .filter(|&(_, v)| v != 0)
+            .collect::<Vec<_>>();
+
+        values.sort();
+        values
+    }
+}
+
+/// What `snd` and `rcv` do.
+pub trait Io {
+    /// Whether `value` could be sent, the machine halting on this `snd` if not.
+    fn send(&mut self, value: i64) -> bool;
+
+    /// The value to store in the register currently holding `current`, or
+    /// `None` to halt the machine on this `rcv`.
+    fn receive(&mut self, current: i64) -> Option<i64>;
+}
+
+/// No devices: sent values are lost and `rcv` always halts.
+impl Io for () {
+    fn send(&mut self, _value: i64) -> bool {
+        true
+    }
+
+    fn receive(&mut self, _current: i64) -> Option<i64> {
+        None
+    }
+}
+
+/// Message queues, `rcv` halting when the inbox is empty.
+#[derive(Debug, Default, Clone)]
+pub struct Mailbox {
+    pub inbox: VecDeque<i64>,
+    pub outbox: VecDeque<i64>,
+}
+
+impl Io for Mailbox {
+    fn send(&mut self, value: i64) -> bool {
+        self.outbox.push_back(value);
+        true
+    }
+
+    fn receive(&mut self, _current: i64) -> Option<i64> {
+        self.inbox.pop_front()
+    }
+}
+
+/// `snd` plays a sound, `rcv` recovers the last one played if its register isn't 0.
+#[derive(Debug, Default, Clone)]
+pub struct Sound {
+    pub played: Option<i64>,
+    pub recovered: Option<i64>,
+}
+
+impl Io for Sound {
+    fn send(&mut self, value: i64) -> bool {
+        self.played = Some(value);
+        true
+    }
+
+    fn receive(&mut self, current: i64) -> Option<i64> {
+        if current != 0 {
+            self.recovered = self.played;
+            None
+        } else {
+            Some(current)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Machine<I, R = LetterRegisters> {
+    program: Vec<I>,
+    registers: R,
+    program_counter: i64,
+}
+
+impl<I: Execute, R: Registers + Default> Machine<I, R> {
+    pub fn new(program: Vec<I>) -> Machine<I, R> {
+        Machine::with_registers(program, R::default())
+    }
+}
+
+impl<I: Execute, R: Registers> Machine<I, R> {
+    pub fn with_registers(program: Vec<I>, registers: R) -> Machine<I, R> {
+        Machine {
+            program,
+            registers,
+            program_counter: 0,
+        }
+    }
+
+    pub fn program(&self) -> &[I] {
+        &self.program
+    }
+
+    pub fn program_counter(&self) -> i64 {
+        self.program_counter
+    }
+
+    pub fn registers(&self) -> &R {
+        &self.registers
+    }
+
+    pub fn registers_mut(&mut self) -> &mut R {
+        &mut self.registers
+    }
+
+    pub fn get(&self, reg: Register) -> i64 {
+        self.registers.get(reg)
+    }
+
+    pub fn set(&mut self, reg: Register, value: i64) {
+        self.registers.set(reg, value);
+    }
+
+    /// The instruction the program counter points to.
+    pub fn current(&self) -> Option<I> {
+        if self.program_counter < 0 {
+            return None;
+        }
+
+        self.program.get(self.program_counter as usize).cloned()
+    }
+
+    /// Executes one instruction, returning it.
+    pub fn step<O: Io>(&mut self, io: &mut O) -> Result<I, Halt> {
+        let instruction = self.current().ok_or(Halt::Exit)?;
+
+        match instruction.execute(&mut self.registers, io)? {
+            Flow::Next => self.program_counter += 1,
+            Flow::Jump(offset) => self.program_counter += offset,
+        }
+
+        Ok(instruction)
+    }
+
+    pub fn run<O: Io>(&mut self, io: &mut O) -> Halt {
+        loop {
+            if let Err(halt) = self.step(io) {
+                return halt;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Instruction::*;
+    use Value::*;
+
+    #[test]
+    fn arithmetic() {
+        let mut machine: Machine<_> = Machine::new(vec![
+            Set('a', Literal(7)),
+            Add('a', Literal(3)),
+            Mul('a', Register('a')),
+            Sub('a', Literal(4)),
+            Mod('a', Literal(5)),
+        ]);
+
+        assert_eq!(machine.run(&mut ()), Halt::Exit);
+        assert_eq!(machine.get('a'), 1);
+        assert_eq!(machine.program_counter(), 5);
+    }
+
+    #[test]
+    fn jumps() {
+        let mut machine: Machine<_> = Machine::new(vec![
+            Set('a', Literal(3)),
+            Add('b', Literal(2)),
+            Sub('a', Literal(1)),
+            Jnz(Register('a'), Literal(-2)),
+            Jgz(Register('a'), Literal(-4)),
+        ]);
+
+        assert_eq!(machine.run(&mut ()), Halt::Exit);
+        assert_eq!(machine.get('b'), 6);
+    }
+
+    #[test]
+    fn receive_resumes() {
+        let mut machine: Machine<_> =
+            Machine::new(vec![Snd(Literal(1)), Rcv('a'), Snd(Register('a'))]);
+        let mut mailbox = Mailbox::default();
+
+        assert_eq!(machine.run(&mut mailbox), Halt::Receive);
+        assert_eq!(machine.program_counter(), 1);
+
+        mailbox.inbox.push_back(42);
+
+        assert_eq!(machine.run(&mut mailbox), Halt::Exit);
+        assert_eq!(mailbox.outbox, vec![1, 42]);
+    }
+
+    #[test]
+    fn division_by_zero() {
+        let mut machine: Machine<_> =
+            Machine::new(vec![Set('a', Literal(1)), Mod('a', Register('b'))]);
+
+        assert_eq!(machine.run(&mut ()), Halt::DivisionByZero);
+        assert_eq!(machine.current(), Some(Mod('a', Register('b'))));
+    }
+
+    #[test]
+    fn hash_map_registers() {
+        let mut machine: Machine<_, HashMap<_, _>> = Machine::new(vec![Set('A', Literal(2))]);
+
+        machine.run(&mut ());
+
+        assert_eq!(machine.get('A'), 2);
+        assert_eq!(machine.get('B'), 0);
+    }
+}
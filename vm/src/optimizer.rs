@@ -0,0 +1,561 @@
+//! Recognizes common loops and replaces them with a single fused operation.
+//!
+//! A fused operation stays at the address of the loop's first instruction,
+//! the rest of the loop being left untouched: when its precondition doesn't
+//! hold, the first instruction is executed as usual and the loop runs slowly.
+
+use std::fmt;
+use std::ops::Range;
+
+use {Execute, Flow, Halt, Instruction, Io, Register, Registers, Value};
+
+/// A loop computing something simpler.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Idiom {
+    /// `target += sign * factor` repeated while `counter` steps to 0.
+    MulAdd {
+        target: Register,
+        factor: Value,
+        sign: i64,
+        counter: Register,
+        /// Whether `counter` is decremented.
+        down: bool,
+    },
+    /// `flag = 0` if `d * e == limit` while `e` counts up to `limit`.
+    DivisibilityTest {
+        d: Value,
+        e: Register,
+        limit: Value,
+        flag: Register,
+        scratch: Register,
+    },
+    /// A divisibility test for every `d` counting up to `limit`, `e`
+    /// restarting from `e_start`: `flag = 0` if `limit` has such a factor pair.
+    CompositeTest {
+        d: Register,
+        e: Register,
+        e_start: Value,
+        limit: Value,
+        flag: Register,
+        scratch: Register,
+    },
+}
+
+impl Idiom {
+    /// Number of instructions replaced.
+    pub fn size(&self) -> usize {
+        match *self {
+            Idiom::MulAdd { .. } => 3,
+            Idiom::DivisibilityTest { .. } => 9,
+            Idiom::CompositeTest { .. } => 14,
+        }
+    }
+
+    /// Runs the whole loop, unless its precondition doesn't hold.
+    fn run<R: Registers>(&self, registers: &mut R) -> bool {
+        match *self {
+            Idiom::MulAdd {
+                target,
+                factor,
+                sign,
+                counter,
+                down,
+            } => {
+                let count = registers.get(counter);
+                let iterations = if down { count } else { -count };
+
+                if iterations <= 0 {
+                    return false;
+                }
+
+                let val = registers.get(target) + sign * registers.value(factor) * iterations;
+                registers.set(target, val);
+                registers.set(counter, 0);
+            }
+            Idiom::DivisibilityTest {
+                d,
+                e,
+                limit,
+                flag,
+                scratch,
+            } => {
+                let (d, from, limit) =
+                    (registers.value(d), registers.get(e), registers.value(limit));
+
+                if from >= limit {
+                    return false;
+                }
+
+                if has_factors(limit, d..d + 1, from..limit) {
+                    registers.set(flag, 0);
+                }
+
+                registers.set(e, limit);
+                registers.set(scratch, 0);
+            }
+            Idiom::CompositeTest {
+                d,
+                e,
+                e_start,
+                limit,
+                flag,
+                scratch,
+            } => {
+                let (from, e_start, limit) = (
+                    registers.get(d),
+                    registers.value(e_start),
+                    registers.value(limit),
+                );
+
+                if from >= limit || e_start >= limit {
+                    return false;
+                }
+
+                if has_factors(limit, from..limit, e_start..limit) {
+                    registers.set(flag, 0);
+                }
+
+                registers.set(d, limit);
+                registers.set(e, limit);
+                registers.set(scratch, 0);
+            }
+        }
+
+        true
+    }
+}
+
+/// Whether `n == a * b` for some `a` in `a_range` and `b` in `b_range`.
+fn has_factors(n: i64, a_range: Range<i64>, b_range: Range<i64>) -> bool {
+    if n == 0 {
+        return (a_range.contains(&0) && !b_range.is_empty())
+            || (b_range.contains(&0) && !a_range.is_empty());
+    }
+
+    let fits = |a: i64, b: i64| a_range.contains(&a) && b_range.contains(&b);
+
+    (1..)
+        .take_while(|&q: &i64| q * q <= n.abs())
+        .filter(|&q| n % q == 0)
+        .any(|q| {
+            let p = n / q;
+
+            fits(q, p) || fits(p, q) || fits(-q, -p) || fits(-p, -q)
+        })
+}
+
+impl fmt::Display for Idiom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Idiom::MulAdd {
+                target,
+                factor,
+                sign,
+                counter,
+                ..
+            } => {
+                let sign = if sign < 0 { "-" } else { "" };
+
+                write!(
+                    f,
+                    "multiply by addition: {} += {}{} * {}",
+                    target, sign, factor, counter
+                )
+            }
+            Idiom::DivisibilityTest {
+                d, e, limit, flag, ..
+            } => write!(
+                f,
+                "divisibility test: {} = 0 if {} * {} == {} for {} up to {}",
+                flag, d, e, limit, e, limit
+            ),
+            Idiom::CompositeTest {
+                d, e, limit, flag, ..
+            } => write!(
+                f,
+                "nested counting loops: {} = 0 if {} * {} == {} for {} and {} up to {}",
+                flag, d, e, limit, d, e, limit
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Op {
+    Plain(Instruction),
+    /// The first instruction of a loop, and what the loop computes.
+    Fused(Instruction, Idiom),
+}
+
+impl Execute for Op {
+    fn execute<R: Registers, O: Io>(self, registers: &mut R, io: &mut O) -> Result<Flow, Halt> {
+        match self {
+            Op::Plain(instruction) => instruction.execute(registers, io),
+            Op::Fused(instruction, idiom) => {
+                if idiom.run(registers) {
+                    Ok(Flow::Jump(idiom.size() as i64))
+                } else {
+                    instruction.execute(registers, io)
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Op::Plain(instruction) => write!(f, "{}", instruction),
+            Op::Fused(instruction, _) => write!(f, "{} *", instruction),
+        }
+    }
+}
+
+/// A loop found by `optimize`.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Rewrite {
+    pub address: usize,
+    pub idiom: Idiom,
+}
+
+impl fmt::Display for Rewrite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}: {}",
+            self.address,
+            self.address + self.idiom.size() - 1,
+            self.idiom
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Optimized {
+    pub program: Vec<Op>,
+    pub rewrites: Vec<Rewrite>,
+}
+
+pub fn optimize(program: &[Instruction]) -> Optimized {
+    let mut optimized = Optimized {
+        program: Vec::with_capacity(program.len()),
+        rewrites: Vec::new(),
+    };
+
+    for (address, &instruction) in program.iter().enumerate() {
+        let code = &program[address..];
+
+        let idiom = composite_test(code)
+            .or_else(|| divisibility_test(code))
+            .or_else(|| mul_add(code));
+
+        optimized.program.push(match idiom {
+            Some(idiom) => {
+                optimized.rewrites.push(Rewrite { address, idiom });
+                Op::Fused(instruction, idiom)
+            }
+            None => Op::Plain(instruction),
+        });
+    }
+
+    optimized
+}
+
+/// The constant added to `reg` by `add reg k` or `sub reg -k`.
+fn increment(instruction: Instruction) -> Option<(Register, i64)> {
+    match instruction {
+        Instruction::Add(reg, Value::Literal(k)) => Some((reg, k)),
+        Instruction::Sub(reg, Value::Literal(k)) => Some((reg, -k)),
+        _ => None,
+    }
+}
+
+/// `target += ±factor`, `factor` being a register or a literal.
+fn accumulate(instruction: Instruction) -> Option<(Register, Value, i64)> {
+    match instruction {
+        Instruction::Add(reg, val) => Some((reg, val, 1)),
+        Instruction::Sub(reg, val) => Some((reg, val, -1)),
+        _ => None,
+    }
+}
+
+fn reads(value: Value, reg: Register) -> bool {
+    value == Value::Register(reg)
+}
+
+/// ```text
+/// add t f     (or sub)
+/// sub c 1     (or add c 1, in any order with the previous one)
+/// jnz c -2
+/// ```
+fn mul_add(code: &[Instruction]) -> Option<Idiom> {
+    let (a, b, jump) = match *code {
+        [a, b, Instruction::Jnz(Value::Register(c), Value::Literal(-2)), ..] => (a, b, c),
+        _ => return None,
+    };
+
+    [(a, b), (b, a)]
+        .iter()
+        .filter_map(|&(acc, count)| {
+            let (target, factor, sign) = accumulate(acc)?;
+            let (counter, step) = increment(count)?;
+
+            if counter != jump || step.abs() != 1 || target == counter {
+                return None;
+            }
+
+            if reads(factor, target) || reads(factor, counter) {
+                return None;
+            }
+
+            Some(Idiom::MulAdd {
+                target,
+                factor,
+                sign,
+                counter,
+                down: step == -1,
+            })
+        })
+        .next()
+}
+
+/// ```text
+/// set g d
+/// mul g e     (or set g e, mul g d)
+/// sub g b
+/// jnz g 2
+/// set f 0
+/// sub e -1
+/// set g e
+/// sub g b
+/// jnz g -8
+/// ```
+fn divisibility_test(code: &[Instruction]) -> Option<Idiom> {
+    use Instruction::*;
+    use Value::{Literal, Register};
+
+    match *code {
+        [Set(g0, x), Mul(g1, y), Sub(g2, b0), Jnz(Register(g3), Literal(2)), Set(f, Literal(0)), step, Set(g4, Register(e0)), Sub(g5, b1), Jnz(Register(g6), Literal(-8)), ..] =>
+        {
+            let (e, inc) = increment(step)?;
+
+            let g = g0;
+            if [g1, g2, g3, g4, g5, g6].iter().any(|&r| r != g) || inc != 1 || e0 != e || b0 != b1 {
+                return None;
+            }
+
+            let d = if x == Register(e) {
+                y
+            } else if y == Register(e) {
+                x
+            } else {
+                return None;
+            };
+
+            let written = [g, e, f];
+            if f == g || f == e || e == g || written.iter().any(|&r| reads(d, r) || reads(b0, r)) {
+                return None;
+            }
+
+            Some(Idiom::DivisibilityTest {
+                d,
+                e,
+                limit: b0,
+                flag: f,
+                scratch: g,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// ```text
+/// set e k
+/// (divisibility test)
+/// sub d -1
+/// set g d
+/// sub g b
+/// jnz g -13
+/// ```
+fn composite_test(code: &[Instruction]) -> Option<Idiom> {
+    use Instruction::*;
+    use Value::{Literal, Register};
+
+    let (e_start, inner) = match *code {
+        [Set(e, k), ..] => (k, (e, divisibility_test(&code[1..])?)),
+        _ => return None,
+    };
+
+    let (d, limit, flag, scratch) = match inner {
+        (
+            e0,
+            Idiom::DivisibilityTest {
+                d: Register(d),
+                e,
+                limit,
+                flag,
+                scratch,
+            },
+        ) if e0 == e => (d, limit, flag, scratch),
+        _ => return None,
+    };
+
+    let e = inner.0;
+
+    match code[10..] {
+        [step, Set(g0, Register(d0)), Sub(g1, b), Jnz(Register(g2), Literal(-13)), ..] => {
+            if increment(step) != Some((d, 1)) || d0 != d || b != limit {
+                return None;
+            }
+
+            if [g0, g1, g2].iter().any(|&r| r != scratch) {
+                return None;
+            }
+
+            if [d, e, flag, scratch].iter().any(|&r| reads(e_start, r)) {
+                return None;
+            }
+
+            Some(Idiom::CompositeTest {
+                d,
+                e,
+                e_start,
+                limit,
+                flag,
+                scratch,
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use grammar::*;
+    use nom::IResult::Done;
+    use Machine;
+
+    named!(
+        line<Instruction>,
+        alt!(snd | rcv | set | add | sub | mul | modulo | jgz | jnz)
+    );
+
+    fn program(text: &str) -> Vec<Instruction> {
+        text.lines()
+            .map(|l| match line(l.trim().as_bytes()) {
+                Done(_, instruction) => instruction,
+                _ => panic!("invalid instruction {}", l),
+            })
+            .collect()
+    }
+
+    /// Runs both versions of `text` after `init`, returning their registers.
+    fn compare(text: &str, init: &[(Register, i64)]) -> (Vec<(Register, i64)>, Vec<Rewrite>) {
+        let plain = program(text);
+        let optimized = optimize(&plain);
+
+        let mut slow: Machine<_> = Machine::new(plain);
+        let mut fast: Machine<_> = Machine::new(optimized.program);
+
+        for &(reg, val) in init {
+            slow.set(reg, val);
+            fast.set(reg, val);
+        }
+
+        assert_eq!(slow.run(&mut ()), Halt::Exit);
+        assert_eq!(fast.run(&mut ()), Halt::Exit);
+
+        assert_eq!(slow.registers().values(), fast.registers().values());
+
+        (fast.registers().values(), optimized.rewrites)
+    }
+
+    const MUL_ADD: &str = "sub a b
+add c -1
+jnz c -2";
+
+    const PRIMES: &str = "set f 1
+set d 2
+set e 2
+set g d
+mul g e
+sub g b
+jnz g 2
+set f 0
+sub e -1
+set g e
+sub g b
+jnz g -8
+sub d -1
+set g d
+sub g b
+jnz g -13
+jnz f 2
+sub h -1
+set g b
+sub g c
+jnz g 2
+jnz 1 3
+sub b -1
+jnz 1 -23";
+
+    #[test]
+    fn mul_add() {
+        let (registers, rewrites) = compare(MUL_ADD, &[('b', 6), ('c', 7)]);
+
+        assert_eq!(registers, vec![('a', -42), ('b', 6)]);
+        assert_eq!(
+            rewrites[0].to_string(),
+            "0-2: multiply by addition: a += -b * c"
+        );
+    }
+
+    #[test]
+    fn mul_add_precondition() {
+        let optimized = optimize(&program(MUL_ADD));
+        let mut registers = ::LetterRegisters::default();
+
+        match optimized.program[0] {
+            Op::Fused(_, idiom) => assert!(!idiom.run(&mut registers)),
+            op => panic!("not fused: {}", op),
+        }
+    }
+
+    #[test]
+    fn divisibility_test() {
+        let inner = PRIMES
+            .lines()
+            .skip(3)
+            .take(9)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        for &(b, f) in &[(12, 0), (13, 1), (4, 1)] {
+            let (registers, rewrites) = compare(&inner, &[('d', 3), ('e', 2), ('b', b), ('f', 1)]);
+
+            assert_eq!(registers.contains(&('f', 1)), f == 1);
+            assert_eq!(rewrites.len(), 1);
+        }
+    }
+
+    #[test]
+    fn count_composites() {
+        let (registers, rewrites) = compare(PRIMES, &[('b', 3), ('c', 60)]);
+
+        assert!(registers.contains(&('h', 42)));
+        assert_eq!(
+            rewrites.iter().map(|r| r.address).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+    }
+
+    #[test]
+    fn has_factors() {
+        assert!(super::has_factors(12, 2..12, 2..12));
+        assert!(!super::has_factors(13, 2..13, 2..13));
+        assert!(super::has_factors(-6, -3..0, 2..3));
+        assert!(!super::has_factors(12, 5..12, 5..12));
+    }
+}
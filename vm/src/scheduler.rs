@@ -0,0 +1,639 @@
+//! Runs several programs exchanging messages with `snd` and `rcv`.
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread;
+
+use {Execute, Halt, Io, LetterRegisters, Machine, Registers};
+
+/// Where the values sent by each program go.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Topology {
+    /// Program `i` sends to program `i + 1`, the last one to the first.
+    Ring,
+    /// Every program sends to all the others.
+    AllToAll,
+    /// Program `i` sends to every program listed in `wiring[i]`.
+    Explicit(Vec<Vec<usize>>),
+}
+
+/// An `Explicit` wiring that doesn't fit the programs.
+#[derive(Debug, Eq, PartialEq)]
+pub enum WiringError {
+    /// The wiring lists destinations for this many programs.
+    Length { programs: usize, wiring: usize },
+    /// `program` sends to `destination`, which doesn't exist.
+    UnknownProgram { program: usize, destination: usize },
+}
+
+impl fmt::Display for WiringError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WiringError::Length { programs, wiring } => write!(
+                f,
+                "wiring for {} programs, but there are {}",
+                wiring, programs
+            ),
+            WiringError::UnknownProgram {
+                program,
+                destination,
+            } => write!(
+                f,
+                "program {} sends to the unknown program {}",
+                program, destination
+            ),
+        }
+    }
+}
+
+impl Error for WiringError {}
+
+impl Topology {
+    /// The destinations of each of `n` programs.
+    pub fn destinations(&self, n: usize) -> Result<Vec<Vec<usize>>, WiringError> {
+        match *self {
+            Topology::Ring => Ok((0..n).map(|i| vec![(i + 1) % n]).collect()),
+            Topology::AllToAll => Ok((0..n)
+                .map(|i| (0..n).filter(|&j| j != i).collect())
+                .collect()),
+            Topology::Explicit(ref wiring) => {
+                if wiring.len() != n {
+                    return Err(WiringError::Length {
+                        programs: n,
+                        wiring: wiring.len(),
+                    });
+                }
+
+                for (program, destinations) in wiring.iter().enumerate() {
+                    if let Some(&destination) = destinations.iter().find(|&&d| d >= n) {
+                        return Err(WiringError::UnknownProgram {
+                            program,
+                            destination,
+                        });
+                    }
+                }
+
+                Ok(wiring.clone())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub struct Stats {
+    /// Values sent, each one reaching every destination.
+    pub sent: usize,
+    pub received: usize,
+    /// Instructions executed.
+    pub steps: u64,
+}
+
+/// How a `Network` run ended.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Outcome {
+    /// Every program left its code or faulted.
+    Terminated,
+    /// These programs wait on each other forever.
+    Deadlock(Vec<usize>),
+}
+
+/// The `snd` and `rcv` of one program.
+struct Port<'a> {
+    id: usize,
+    inboxes: &'a mut [VecDeque<i64>],
+    destinations: &'a [usize],
+    capacity: Option<usize>,
+    stats: &'a mut Stats,
+}
+
+impl<'a> Io for Port<'a> {
+    fn send(&mut self, value: i64) -> bool {
+        let capacity = self.capacity;
+        let inboxes = &mut *self.inboxes;
+
+        let full = self
+            .destinations
+            .iter()
+            .any(|&d| capacity.is_some_and(|c| inboxes[d].len() >= c));
+
+        if full {
+            return false;
+        }
+
+        for &d in self.destinations {
+            inboxes[d].push_back(value);
+        }
+
+        self.stats.sent += 1;
+        true
+    }
+
+    fn receive(&mut self, _current: i64) -> Option<i64> {
+        let value = self.inboxes[self.id].pop_front()?;
+
+        self.stats.received += 1;
+        Some(value)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+enum State {
+    Running,
+    Receiving,
+    Sending,
+    Stopped,
+}
+
+/// What every thread knows of the others, updated under a single lock so a
+/// deadlock can't be missed or reported while a value is on its way.
+struct Coordinator {
+    states: Vec<State>,
+    /// Values sent to each program and not yet received.
+    queued: Vec<usize>,
+    capacity: Option<usize>,
+    deadlock: bool,
+}
+
+impl Coordinator {
+    fn can_send(&self, destinations: &[usize]) -> bool {
+        match self.capacity {
+            Some(c) => destinations.iter().all(|&d| self.queued[d] < c),
+            None => true,
+        }
+    }
+
+    fn is_deadlocked(&self, destinations: &[Vec<usize>]) -> bool {
+        let blocked = self
+            .states
+            .iter()
+            .enumerate()
+            .all(|(id, &state)| match state {
+                State::Running => false,
+                State::Receiving => self.queued[id] == 0,
+                State::Sending => !self.can_send(&destinations[id]),
+                State::Stopped => true,
+            });
+
+        blocked && self.states.iter().any(|&state| state != State::Stopped)
+    }
+}
+
+struct Shared {
+    coordinator: Mutex<Coordinator>,
+    changed: Condvar,
+    destinations: Vec<Vec<usize>>,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Coordinator> {
+        self.coordinator.lock().expect("a program thread panicked")
+    }
+
+    /// Puts `id` in `state` until `ready`, or `None` once every program is blocked.
+    fn block<'a, F>(
+        &self,
+        mut coordinator: MutexGuard<'a, Coordinator>,
+        id: usize,
+        state: State,
+        ready: F,
+    ) -> Option<MutexGuard<'a, Coordinator>>
+    where
+        F: Fn(&Coordinator) -> bool,
+    {
+        coordinator.states[id] = state;
+
+        if coordinator.is_deadlocked(&self.destinations) {
+            coordinator.deadlock = true;
+            self.changed.notify_all();
+        }
+
+        while !ready(&coordinator) {
+            if coordinator.deadlock {
+                return None;
+            }
+
+            coordinator = self
+                .changed
+                .wait(coordinator)
+                .expect("a program thread panicked");
+        }
+
+        coordinator.states[id] = State::Running;
+        Some(coordinator)
+    }
+
+    fn stop(&self, id: usize) {
+        let mut coordinator = self.lock();
+        coordinator.states[id] = State::Stopped;
+
+        if coordinator.is_deadlocked(&self.destinations) {
+            coordinator.deadlock = true;
+            self.changed.notify_all();
+        }
+    }
+}
+
+/// The `snd` and `rcv` of a program running on its own thread.
+struct ThreadPort<'a> {
+    id: usize,
+    inbox: Receiver<i64>,
+    outboxes: Vec<(usize, Sender<i64>)>,
+    shared: &'a Shared,
+    stats: &'a mut Stats,
+}
+
+impl<'a> Io for ThreadPort<'a> {
+    fn send(&mut self, value: i64) -> bool {
+        let destinations = &self.shared.destinations[self.id];
+        let mut coordinator = self.shared.lock();
+
+        if !coordinator.can_send(destinations) {
+            coordinator = match self
+                .shared
+                .block(coordinator, self.id, State::Sending, |c| {
+                    c.can_send(destinations)
+                }) {
+                Some(coordinator) => coordinator,
+                None => return false,
+            };
+        }
+
+        for &(d, ref outbox) in &self.outboxes {
+            outbox.send(value).expect("inbox dropped while running");
+            coordinator.queued[d] += 1;
+        }
+
+        self.shared.changed.notify_all();
+
+        self.stats.sent += 1;
+        true
+    }
+
+    fn receive(&mut self, _current: i64) -> Option<i64> {
+        let id = self.id;
+        let mut coordinator = self.shared.lock();
+
+        if coordinator.queued[id] == 0 {
+            coordinator = self
+                .shared
+                .block(coordinator, id, State::Receiving, |c| c.queued[id] > 0)?;
+        }
+
+        coordinator.queued[id] -= 1;
+        self.shared.changed.notify_all();
+
+        let value = self
+            .inbox
+            .try_recv()
+            .expect("queued value not in the inbox");
+
+        self.stats.received += 1;
+        Some(value)
+    }
+}
+
+/// Programs running in turn, each one until it blocks, over message queues.
+pub struct Network<I, R = LetterRegisters> {
+    machines: Vec<Machine<I, R>>,
+    inboxes: Vec<VecDeque<i64>>,
+    destinations: Vec<Vec<usize>>,
+    capacity: Option<usize>,
+    stats: Vec<Stats>,
+    halts: Vec<Option<Halt>>,
+}
+
+impl<I: Execute, R: Registers> Network<I, R> {
+    pub fn new(
+        machines: Vec<Machine<I, R>>,
+        topology: &Topology,
+    ) -> Result<Network<I, R>, WiringError> {
+        let n = machines.len();
+
+        Ok(Network {
+            machines,
+            inboxes: vec![VecDeque::new(); n],
+            destinations: topology.destinations(n)?,
+            capacity: None,
+            stats: vec![Stats::default(); n],
+            halts: vec![None; n],
+        })
+    }
+
+    /// Limits every queue to `capacity` values, a `snd` blocking while one of
+    /// its destinations is full.
+    pub fn bounded(mut self, capacity: usize) -> Network<I, R> {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    pub fn machines(&self) -> &[Machine<I, R>] {
+        &self.machines
+    }
+
+    /// The values waiting to be received by each program.
+    pub fn queues(&self) -> &[VecDeque<i64>] {
+        &self.inboxes
+    }
+
+    pub fn stats(&self) -> &[Stats] {
+        &self.stats
+    }
+
+    /// Why each program last stopped, `None` if it never ran.
+    pub fn halts(&self) -> &[Option<Halt>] {
+        &self.halts
+    }
+
+    /// Runs every program until all of them have terminated or are blocked.
+    pub fn run(&mut self) -> Outcome {
+        loop {
+            let mut progress = false;
+
+            for id in 0..self.machines.len() {
+                match self.halts[id] {
+                    Some(Halt::Exit) | Some(Halt::DivisionByZero) => continue,
+                    _ => (),
+                }
+
+                let mut port = Port {
+                    id,
+                    inboxes: &mut self.inboxes,
+                    destinations: &self.destinations[id],
+                    capacity: self.capacity,
+                    stats: &mut self.stats[id],
+                };
+
+                let steps = port.stats.steps;
+
+                let halt = loop {
+                    match self.machines[id].step(&mut port) {
+                        Ok(_) => port.stats.steps += 1,
+                        Err(halt) => break halt,
+                    }
+                };
+
+                progress |= port.stats.steps != steps;
+                self.halts[id] = Some(halt);
+            }
+
+            if !progress {
+                break;
+            }
+        }
+
+        let blocked = self
+            .halts
+            .iter()
+            .enumerate()
+            .filter(|&(_, &halt)| halt == Some(Halt::Receive) || halt == Some(Halt::Send))
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+
+        if blocked.is_empty() {
+            Outcome::Terminated
+        } else {
+            Outcome::Deadlock(blocked)
+        }
+    }
+}
+
+impl<I: Execute + Send, R: Registers + Send> Network<I, R> {
+    /// Same as `run`, each program running on its own thread.
+    ///
+    /// The counts are the same, but values from different senders may reach an
+    /// inbox in another order.
+    pub fn run_threaded(&mut self) -> Outcome {
+        let n = self.machines.len();
+
+        let (outboxes, inboxes): (Vec<_>, Vec<_>) = (0..n).map(|_| mpsc::channel()).unzip();
+
+        let shared = Shared {
+            coordinator: Mutex::new(Coordinator {
+                states: vec![State::Running; n],
+                queued: self.inboxes.iter().map(|q| q.len()).collect(),
+                capacity: self.capacity,
+                deadlock: false,
+            }),
+            changed: Condvar::new(),
+            destinations: self.destinations.clone(),
+        };
+
+        for (queue, outbox) in self.inboxes.iter_mut().zip(&outboxes) {
+            for value in queue.drain(..) {
+                outbox.send(value).expect("inbox dropped");
+            }
+        }
+
+        let shared = &shared;
+
+        let results = thread::scope(|scope| {
+            let threads = self
+                .machines
+                .iter_mut()
+                .zip(self.stats.iter_mut())
+                .zip(inboxes)
+                .enumerate()
+                .map(|(id, ((machine, stats), inbox))| {
+                    let mut port = ThreadPort {
+                        id,
+                        inbox,
+                        outboxes: shared.destinations[id]
+                            .iter()
+                            .map(|&d| (d, outboxes[d].clone()))
+                            .collect(),
+                        shared,
+                        stats,
+                    };
+
+                    scope.spawn(move || {
+                        let halt = loop {
+                            match machine.step(&mut port) {
+                                Ok(_) => port.stats.steps += 1,
+                                Err(halt) => break halt,
+                            }
+                        };
+
+                        if halt != Halt::Receive && halt != Halt::Send {
+                            shared.stop(id);
+                        }
+
+                        (halt, port.inbox)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            threads
+                .into_iter()
+                .map(|thread| thread.join().expect("a program thread panicked"))
+                .collect::<Vec<_>>()
+        });
+
+        drop(outboxes);
+
+        let mut blocked = Vec::new();
+
+        for (id, (halt, inbox)) in results.into_iter().enumerate() {
+            self.halts[id] = Some(halt);
+            self.inboxes[id].extend(inbox.try_iter());
+
+            if halt == Halt::Receive || halt == Halt::Send {
+                blocked.push(id);
+            }
+        }
+
+        if blocked.is_empty() {
+            Outcome::Terminated
+        } else {
+            Outcome::Deadlock(blocked)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use Instruction::{self, *};
+    use Value::*;
+
+    /// Sends 1, 2 and its id, then waits for 4 values.
+    fn duet() -> Vec<Instruction> {
+        vec![
+            Snd(Literal(1)),
+            Snd(Literal(2)),
+            Snd(Register('p')),
+            Rcv('a'),
+            Rcv('b'),
+            Rcv('c'),
+            Rcv('d'),
+        ]
+    }
+
+    fn network(n: i64, program: &[Instruction], topology: &Topology) -> Network<Instruction> {
+        let machines = (0..n)
+            .map(|p| {
+                let mut machine = Machine::new(program.to_vec());
+                machine.set('p', p);
+                machine
+            })
+            .collect();
+
+        Network::new(machines, topology).unwrap()
+    }
+
+    #[test]
+    fn destinations() {
+        assert_eq!(
+            Topology::Ring.destinations(3),
+            Ok(vec![vec![1], vec![2], vec![0]])
+        );
+        assert_eq!(
+            Topology::AllToAll.destinations(3),
+            Ok(vec![vec![1, 2], vec![0, 2], vec![0, 1]])
+        );
+    }
+
+    #[test]
+    fn invalid_wiring() {
+        let machines = || -> Vec<Machine<_>> { vec![Machine::new(duet()), Machine::new(duet())] };
+
+        assert_eq!(
+            Network::new(machines(), &Topology::Explicit(vec![vec![1]])).err(),
+            Some(WiringError::Length {
+                programs: 2,
+                wiring: 1
+            })
+        );
+        assert_eq!(
+            Network::new(machines(), &Topology::Explicit(vec![vec![1], vec![0, 2]])).err(),
+            Some(WiringError::UnknownProgram {
+                program: 1,
+                destination: 2
+            })
+        );
+    }
+
+    #[test]
+    fn ring_of_two() {
+        let mut network = network(2, &duet(), &Topology::Ring);
+
+        assert_eq!(network.run(), Outcome::Deadlock(vec![0, 1]));
+        assert_eq!(network.stats()[1].sent, 3);
+        assert_eq!(network.machines()[0].get('c'), 1);
+        assert_eq!(network.machines()[1].get('c'), 0);
+    }
+
+    #[test]
+    fn all_to_all() {
+        let mut network = network(3, &duet(), &Topology::AllToAll);
+
+        assert_eq!(network.run(), Outcome::Terminated);
+        assert!(network
+            .stats()
+            .iter()
+            .all(|s| s.sent == 3 && s.received == 4));
+        assert_eq!(
+            network.queues().iter().map(|q| q.len()).collect::<Vec<_>>(),
+            vec![2, 2, 2]
+        );
+    }
+
+    #[test]
+    fn explicit_wiring() {
+        let wiring = Topology::Explicit(vec![vec![1, 2], vec![], vec![]]);
+        let mut network = network(3, &duet()[..3], &wiring);
+
+        assert_eq!(network.run(), Outcome::Terminated);
+        assert_eq!(network.queues()[0].len(), 0);
+        assert_eq!(network.queues()[2], vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn threaded() {
+        for topology in &[Topology::Ring, Topology::AllToAll] {
+            let mut cooperative = network(5, &duet(), topology);
+            let mut threaded = network(5, &duet(), topology);
+
+            assert_eq!(cooperative.run(), threaded.run_threaded());
+            assert_eq!(cooperative.stats(), threaded.stats());
+
+            // Values from different senders may interleave in any order.
+            let contents = |network: &Network<Instruction>| {
+                network
+                    .queues()
+                    .iter()
+                    .map(|q| {
+                        let mut values = q.iter().cloned().collect::<Vec<_>>();
+                        values.sort();
+                        values
+                    })
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(contents(&cooperative), contents(&threaded));
+        }
+    }
+
+    #[test]
+    fn threaded_bounded_queues() {
+        let program = vec![Snd(Literal(1)), Jnz(Literal(1), Literal(-1))];
+        let mut network = network(3, &program, &Topology::Ring).bounded(5);
+
+        assert_eq!(network.run_threaded(), Outcome::Deadlock(vec![0, 1, 2]));
+        assert!(network.halts().iter().all(|&h| h == Some(Halt::Send)));
+        assert!(network.queues().iter().all(|q| q.iter().eq(&[1; 5])));
+    }
+
+    #[test]
+    fn bounded_queues() {
+        let program = vec![Snd(Literal(1)), Jnz(Literal(1), Literal(-1))];
+        let mut network = network(2, &program, &Topology::Ring).bounded(5);
+
+        assert_eq!(network.run(), Outcome::Deadlock(vec![0, 1]));
+        assert_eq!(network.halts()[0], Some(Halt::Send));
+        assert_eq!(network.stats()[0].sent, 5);
+        assert_eq!(network.queues()[1].len(), 5);
+    }
+}